@@ -0,0 +1,20 @@
+CREATE TABLE sessions (
+    id TEXT PRIMARY KEY NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
+
+CREATE TABLE session_turns (
+    id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+    session_id TEXT NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    turn_index INTEGER NOT NULL,
+    question TEXT NOT NULL,
+    generated_sql TEXT,
+    summary TEXT,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    duration_ms BIGINT,
+    result_preview TEXT,
+    feedback TEXT,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (session_id, turn_index)
+);
@@ -0,0 +1,8 @@
+CREATE TABLE api_keys (
+    name TEXT PRIMARY KEY NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    datasources TEXT NOT NULL DEFAULT '*',
+    allowed_tables TEXT NOT NULL DEFAULT '*',
+    allow_write INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL DEFAULT (datetime('now'))
+);
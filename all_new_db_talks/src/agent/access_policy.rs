@@ -0,0 +1,206 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    agent::sql_validation::SqlValidator,
+    configuration::auth_config::AuthConfig,
+    datasource::{api_key_store::ApiKeyStore, db_utill::DatabaseSchema},
+};
+
+/// Matches every datasource or table
+pub const ANY: &str = "*";
+const KEY_PREFIX: &str = "tdb_";
+
+/// What one api key may do. Only the sha256 hash of the key is ever stored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKeyPolicy {
+    pub name: String,
+    pub key_hash: String,
+    #[serde(default = "any")]
+    pub datasources: Vec<String>,
+    #[serde(default = "any")]
+    pub tables: Vec<String>,
+    #[serde(default)]
+    pub allow_write: bool,
+}
+
+fn any() -> Vec<String> {
+    vec![ANY.to_string()]
+}
+
+/// Why a request was refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AccessDenied {
+    /// No key, or a key that matches no policy
+    Unauthenticated,
+    Forbidden(String),
+    /// The key store could not be read, so the key could be neither accepted nor refused
+    Unavailable(String),
+}
+
+pub fn hash_api_key(key: &str) -> String {
+    Sha256::digest(key.trim().as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// A new random key; show it once and keep only its hash
+pub fn generate_api_key() -> String {
+    let mut bytes = [0u8; 24];
+    rand::rng().fill_bytes(&mut bytes);
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("{}{}", KEY_PREFIX, hex)
+}
+
+fn allows(list: &[String], name: &str) -> bool {
+    list.iter().any(|entry| entry == ANY || entry.eq_ignore_ascii_case(name))
+}
+
+impl ApiKeyPolicy {
+    pub fn new(name: &str, key: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            key_hash: hash_api_key(key),
+            datasources: any(),
+            tables: any(),
+            allow_write: false,
+        }
+    }
+
+    /// Checked before any sql is generated
+    pub fn check_datasource(&self, datasource: &str) -> Result<(), AccessDenied> {
+        if allows(&self.datasources, datasource) {
+            return Ok(());
+        }
+        Err(AccessDenied::Forbidden(format!(
+            "key '{}' may not use datasource '{}'",
+            self.name, datasource
+        )))
+    }
+
+    /// Checked after generation and before execution
+    pub fn check_sql(&self, sql: &str) -> Result<(), AccessDenied> {
+        if SqlValidator::is_write(sql) && !self.allow_write {
+            return Err(AccessDenied::Forbidden(format!("key '{}' may not modify data", self.name)));
+        }
+        if self.tables.iter().any(|entry| entry == ANY) {
+            return Ok(());
+        }
+        if !SqlValidator::is_query(sql) {
+            return Err(AccessDenied::Forbidden(format!(
+                "key '{}' is limited to some tables and may only run SELECT or WITH statements",
+                self.name
+            )));
+        }
+        // a table list that cannot be fully read could hide a table the key may not see
+        let tables = SqlValidator::tables_in(sql)
+            .map_err(|reason| AccessDenied::Forbidden(format!("key '{}' may only run sql whose tables can be checked: {}", self.name, reason)))?;
+        let denied: Vec<String> = tables.into_iter().filter(|table| !allows(&self.tables, table)).collect();
+        if !denied.is_empty() {
+            return Err(AccessDenied::Forbidden(format!(
+                "key '{}' may not read table(s) {}",
+                self.name,
+                denied.join(", ")
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn may_read(&self, table: &str) -> bool {
+        allows(&self.tables, table)
+    }
+
+    /// Drop the tables this key may not see
+    pub fn filter_schema(&self, schema: DatabaseSchema) -> DatabaseSchema {
+        DatabaseSchema {
+            schemas: schema
+                .schemas
+                .into_iter()
+                .filter(|table| allows(&self.tables, &table.table_name))
+                .collect(),
+        }
+    }
+}
+
+/// Looks presented keys up in the configured keys first, then in the SQLite store.
+/// Store lookups are blocking diesel calls and run on the blocking pool.
+pub struct Authenticator {
+    pub keys: Vec<ApiKeyPolicy>,
+    pub store: Option<Arc<Mutex<ApiKeyStore>>>,
+    pub disabled: bool,
+}
+
+impl Authenticator {
+    pub fn new(config: &AuthConfig, store: Option<ApiKeyStore>) -> Result<Self> {
+        Ok(Self {
+            keys: config.load_keys()?,
+            store: store.map(|store| Arc::new(Mutex::new(store))),
+            disabled: config.disabled,
+        })
+    }
+
+    /// `Ok(None)` means authentication is disabled and everything is allowed
+    pub async fn authenticate(&self, presented: Option<&str>) -> Result<Option<ApiKeyPolicy>, AccessDenied> {
+        if self.disabled {
+            return Ok(None);
+        }
+        let key = presented.filter(|key| !key.trim().is_empty()).ok_or(AccessDenied::Unauthenticated)?;
+        let hash = hash_api_key(key);
+
+        if let Some(policy) = self.keys.iter().find(|policy| policy.key_hash == hash) {
+            return Ok(Some(policy.clone()));
+        }
+        let Some(store) = self.store.clone() else {
+            return Err(AccessDenied::Unauthenticated);
+        };
+        let lookup = tokio::task::spawn_blocking(move || {
+            store.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).find_by_hash(&hash)
+        })
+        .await;
+        match lookup {
+            Ok(Ok(Some(policy))) => Ok(Some(policy)),
+            Ok(Ok(None)) => Err(AccessDenied::Unauthenticated),
+            Ok(Err(err)) => Err(AccessDenied::Unavailable(format!("failed to look up api key: {}", err))),
+            Err(err) => Err(AccessDenied::Unavailable(format!("api key lookup did not finish: {}", err))),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{generate_api_key, hash_api_key, AccessDenied, ApiKeyPolicy};
+
+    #[test]
+    fn test_policy_checks_tables_and_writes() {
+        let key = generate_api_key();
+        let mut policy = ApiKeyPolicy::new("reporting", &key);
+        policy.datasources = vec!["sales".to_string()];
+        policy.tables = vec!["Invoice".to_string()];
+
+        assert_eq!(policy.key_hash, hash_api_key(&key));
+        assert!(policy.check_datasource("sales").is_ok());
+        assert!(policy.check_datasource("hr").is_err());
+        assert!(policy.check_sql("WITH t AS (SELECT Total FROM invoice) SELECT * FROM t").is_ok());
+        assert!(matches!(
+            policy.check_sql("SELECT * FROM Employee"),
+            Err(AccessDenied::Forbidden(message)) if message.contains("employee")
+        ));
+        assert!(policy.check_sql("DELETE FROM Invoice").is_err());
+        assert!(matches!(
+            policy.check_sql("SELECT * FROM Invoice i, Employee e WHERE i.CustomerId = e.EmployeeId"),
+            Err(AccessDenied::Forbidden(message)) if message.contains("employee")
+        ));
+        for statement in ["DESCRIBE Employee", "SHOW CREATE TABLE Employee", "SHOW COLUMNS IN Employee", "EXPLAIN SELECT * FROM Employee"] {
+            assert!(matches!(policy.check_sql(statement), Err(AccessDenied::Forbidden(_))), "{}", statement);
+        }
+        let open = ApiKeyPolicy::new("admin", &key);
+        assert!(open.check_sql("DESCRIBE Employee").is_ok());
+        assert!(policy.check_sql("SELECT * FROM Invoice, JSON_TABLE('[]', '$[*]' COLUMNS (x INT PATH '$')) j").is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use anyhow::{anyhow, Error};
+use serde::{Deserialize, Serialize};
+
+use crate::{agent::model_router::{unreported, ModelRouter}, configuration::model_config::ModelTask};
+
+/// Returned instead of sql when the question can be read in several ways
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ClarificationRequest {
+    pub question: String,
+    pub reason: String,
+    pub options: Vec<String>,
+}
+
+impl ClarificationRequest {
+    /// Turn the user's answer into a precise question.
+    /// `choice` is either a 1-based option number or free text.
+    pub fn resolve(&self, choice: &str) -> Result<String, Error> {
+        let choice = choice.trim();
+        if choice.is_empty() {
+            return Err(anyhow!("empty clarification choice"));
+        }
+
+        let interpretation = match choice.parse::<usize>() {
+            Ok(index) => self
+                .options
+                .get(index.wrapping_sub(1))
+                .cloned()
+                .ok_or_else(|| anyhow!("choice {} is out of range (1-{})", index, self.options.len()))?,
+            Err(_) => choice.to_string(),
+        };
+
+        Ok(format!("{} (meaning: {})", self.question.trim(), interpretation))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ClarificationVerdict {
+    #[serde(default)]
+    ambiguous: bool,
+    #[serde(default)]
+    reason: String,
+    #[serde(default)]
+    options: Vec<String>,
+}
+
+pub struct ClarificationDetector {
+    pub router: ModelRouter,
+}
+
+impl ClarificationDetector {
+    pub fn new(router: ModelRouter) -> Self {
+        Self { router }
+    }
+
+    /// Ask the clarification model whether the question needs a follow-up before writing sql
+    pub async fn check(&self, question: &str, schema: &str, history: &str) -> Result<Option<ClarificationRequest>, Error> {
+        let prompt = Self::construct_prompt(question, schema, history);
+        let routed = self.router.generate(ModelTask::Clarification, prompt, &unreported).await?;
+        Ok(Self::parse_verdict(question, &routed.response))
+    }
+
+    pub fn construct_prompt(question: &str, schema: &str, history: &str) -> String {
+        let history = if history.is_empty() { "(none)" } else { history };
+        format!(
+            "You review questions before they are turned into SQL.
+
+            Database Schema:
+            {}
+
+            Conversation so far:
+            {}
+
+            User Question:
+            {}
+
+            Instructions:
+            - Decide if the question has more than one reasonable interpretation
+              for this schema (e.g. \"top customers\" by revenue or by invoice count).
+            - Only flag real ambiguity that would change the SQL result.
+            - Reply with JSON only, in this shape:
+              {{\"ambiguous\": true, \"reason\": \"...\", \"options\": [\"...\", \"...\"]}}
+              or {{\"ambiguous\": false}}
+            - A follow-up that refines the previous query is not ambiguous.",
+            schema,
+            history,
+            question.trim()
+        )
+    }
+
+    /// Anything that is not a well formed "ambiguous" verdict with two or more options is treated as clear
+    pub fn parse_verdict(question: &str, output: &str) -> Option<ClarificationRequest> {
+        let start = output.find('{')?;
+        let end = output.rfind('}')?;
+        if end < start {
+            return None;
+        }
+        let verdict: ClarificationVerdict = serde_json::from_str(&output[start..=end]).ok()?;
+
+        let options: Vec<String> = verdict
+            .options
+            .into_iter()
+            .map(|option| option.trim().to_string())
+            .filter(|option| !option.is_empty())
+            .collect();
+        if !verdict.ambiguous || options.len() < 2 {
+            return None;
+        }
+
+        Some(ClarificationRequest {
+            question: question.trim().to_string(),
+            reason: verdict.reason,
+            options,
+        })
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::ClarificationDetector;
+
+    #[test]
+    fn test_parse_ambiguous_verdict() {
+        let output = r#"Sure: {"ambiguous": true, "reason": "ranking metric", "options": ["by revenue", "by invoice count"]}"#;
+        let request = ClarificationDetector::parse_verdict("top customers", output).unwrap();
+        assert_eq!(request.options.len(), 2);
+        assert_eq!(request.resolve("2").unwrap(), "top customers (meaning: by invoice count)");
+        assert!(request.resolve("3").is_err());
+    }
+
+    #[test]
+    fn test_parse_clear_verdict() {
+        assert!(ClarificationDetector::parse_verdict("q", r#"{"ambiguous": false}"#).is_none());
+        assert!(ClarificationDetector::parse_verdict("q", "not json").is_none());
+    }
+}
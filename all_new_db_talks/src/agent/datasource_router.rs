@@ -0,0 +1,257 @@
+use std::{collections::HashSet, sync::Arc};
+
+use anyhow::{anyhow, Error};
+use ollama_rs::Ollama;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::{
+    agent::{model_router::ModelRouter, text_to_sql::TextToSqlChain},
+    configuration::{
+        app_config::AppConfig,
+        datasource_config::{DatasourceConfig, DatasourceKind, DEFAULT_DATASOURCE},
+    },
+    datasource::{csv_utill::CsvUtill, pool::PoolHealth},
+};
+
+/// One connected datasource with the words its schema is known by
+pub struct Datasource {
+    pub name: String,
+    pub kind: DatasourceKind,
+    pub description: String,
+    pub chain: Arc<TextToSqlChain>,
+    /// Table and column names plus description words, lowercased and split
+    terms: HashSet<String>,
+}
+
+/// What callers see of a datasource
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DatasourceInfo {
+    pub name: String,
+    pub kind: DatasourceKind,
+    pub description: String,
+}
+
+/// Health of one datasource; csv datasources have no pool and are healthy
+/// while their file exists
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct DatasourceHealth {
+    pub name: String,
+    pub healthy: bool,
+    pub pool: Option<PoolHealth>,
+}
+
+impl Datasource {
+    async fn new(name: String, profile: &DatasourceConfig, chain: TextToSqlChain) -> Self {
+        let mut terms: HashSet<String> = words(&profile.description).collect();
+        match chain.get_db_info().await {
+            Ok(schema) => terms.extend(words(&schema.to_string())),
+            Err(err) => eprintln!("Schema of datasource '{}' unavailable, routing by description only: {}", name, err),
+        }
+        Self {
+            name,
+            kind: profile.kind,
+            description: profile.description.clone(),
+            chain: Arc::new(chain),
+            terms,
+        }
+    }
+
+    /// How many distinct words of `text` name something in this datasource
+    pub fn relevance(&self, text: &str) -> usize {
+        words(text).collect::<HashSet<_>>().intersection(&self.terms).count()
+    }
+
+    pub fn info(&self) -> DatasourceInfo {
+        DatasourceInfo { name: self.name.clone(), kind: self.kind, description: self.description.clone() }
+    }
+
+    /// Pings the pool, which reconnects when the server went away
+    pub async fn health(&self) -> DatasourceHealth {
+        let pool = self.chain.health().await;
+        let healthy = match &pool {
+            Some(pool) => pool.healthy,
+            None => self.chain.csv.as_ref().is_some_and(CsvUtill::verify_path),
+        };
+        DatasourceHealth { name: self.name.clone(), healthy, pool }
+    }
+}
+
+/// Every configured datasource of a deployment. A question either names
+/// its datasource or goes to the one whose schema it mentions most.
+pub struct DatasourceRouter {
+    pub sources: Vec<Datasource>,
+    /// Used when routing cannot tell; `[chain] datasource`, else `default` when configured
+    pub default: Option<String>,
+}
+
+impl DatasourceRouter {
+    /// Connect every profile of an already validated configuration
+    pub async fn connect(config: &AppConfig) -> Result<Self, Error> {
+        let client = Ollama::new(&config.llm.url, config.llm.port);
+        let router = ModelRouter::new(client.clone(), config.models.clone(), config.limits.model_concurrency);
+        let profiles = config.datasource_profiles();
+
+        let mut sources = Vec::new();
+        for (name, profile) in &profiles {
+            let mut chain = TextToSqlChain::connect_datasource(config, name, profile, client.clone(), router.clone())
+                .await
+                .map_err(|err| anyhow!("failed to connect datasource '{}': {}", name, err))?;
+            if let (false, Some(csv_path)) = (profile.is_csv(), &config.chain.csv_file) {
+                chain.attach_csv(CsvUtill::new(csv_path.clone()));
+            }
+            sources.push(Datasource::new(name.clone(), profile, chain).await);
+        }
+
+        let default = config
+            .chain
+            .datasource
+            .clone()
+            .or_else(|| profiles.contains_key(DEFAULT_DATASOURCE).then(|| DEFAULT_DATASOURCE.to_string()));
+        Ok(Self { sources, default })
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Datasource, Error> {
+        self.sources
+            .iter()
+            .find(|source| source.name == name)
+            .ok_or_else(|| anyhow!("unknown datasource '{}', use one of: {}", name, self.names().join(", ")))
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.sources.iter().map(|source| source.name.as_str()).collect()
+    }
+
+    pub fn list(&self) -> Vec<DatasourceInfo> {
+        self.sources.iter().map(Datasource::info).collect()
+    }
+
+    pub async fn health(&self) -> Vec<DatasourceHealth> {
+        let mut health = Vec::new();
+        for source in &self.sources {
+            health.push(source.health().await);
+        }
+        health
+    }
+
+    /// `name`, else the default, else the only datasource
+    pub fn named(&self, name: Option<&str>) -> Result<&Datasource, Error> {
+        match (name.or(self.default.as_deref()), self.sources.as_slice()) {
+            (Some(name), _) => self.get(name),
+            (None, [only]) => Ok(only),
+            (None, _) => Err(anyhow!("several datasources are configured, name one of: {}", self.names().join(", "))),
+        }
+    }
+
+    /// The requested datasource, or the `allowed` one whose schema best matches `text`
+    pub fn pick(&self, text: &str, requested: Option<&str>, allowed: &dyn Fn(&str) -> bool) -> Result<&Datasource, Error> {
+        if let Some(name) = requested {
+            return self.get(name);
+        }
+        let candidates: Vec<&Datasource> = self.sources.iter().filter(|source| allowed(&source.name)).collect();
+        let scored: Vec<(&str, usize)> =
+            candidates.iter().map(|source| (source.name.as_str(), source.relevance(text))).collect();
+        let default = self.default.as_deref();
+        match best_match(&scored, default) {
+            Some(name) => self.get(name),
+            None if candidates.is_empty() => Err(anyhow!("no datasource is available")),
+            None => Err(anyhow!(
+                "could not tell which datasource the question is about, name one of: {}",
+                candidates.iter().map(|source| source.name.as_str()).collect::<Vec<_>>().join(", ")
+            )),
+        }
+    }
+
+    /// Take one chain out, e.g. for the REPL which works on a single datasource
+    pub fn into_chain(mut self, name: Option<&str>) -> Result<TextToSqlChain, Error> {
+        let name = self.named(name)?.name.clone();
+        let position = self.sources.iter().position(|source| source.name == name).unwrap_or_default();
+        let source = self.sources.swap_remove(position);
+        Arc::try_unwrap(source.chain).map_err(|_| anyhow!("datasource '{}' is still in use", name))
+    }
+}
+
+/// Highest scoring name; with no match at all, the default (when it is a candidate) or the only candidate.
+/// Ties go to the default, then to the first candidate.
+fn best_match<'a>(scored: &[(&'a str, usize)], default: Option<&str>) -> Option<&'a str> {
+    if let [(only, _)] = scored {
+        return Some(*only);
+    }
+    let is_default = |name: &str| Some(name) == default;
+    let best = scored.iter().map(|(_, score)| *score).max()?;
+    let mut leaders = scored.iter().filter(|(_, score)| *score == best).map(|(name, _)| *name);
+    if best == 0 {
+        return leaders.find(|name| is_default(name));
+    }
+    let leaders: Vec<&str> = leaders.collect();
+    leaders.iter().copied().find(|name| is_default(name)).or(leaders.first().copied())
+}
+
+/// Words too common in questions and descriptions to say anything about a schema
+const STOP_WORDS: &[&str] = &[
+    "the", "and", "for", "with", "from", "what", "which", "who", "how", "many", "much", "per", "all", "are",
+    "was", "were", "has", "have", "show", "list", "give", "each", "that", "this", "table", "data",
+];
+
+/// Lowercased words of identifiers and prose: `InvoiceLine` gives `invoiceline`, `invoice`, `line`.
+/// A trailing plural `s` is dropped so `customers` matches `Customer`.
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .flat_map(|word| {
+            let mut parts = vec![word.to_string()];
+            let mut current = String::new();
+            for c in word.chars() {
+                if c.is_uppercase() && !current.is_empty() {
+                    parts.push(std::mem::take(&mut current));
+                }
+                current.push(c);
+            }
+            if current.len() < word.len() {
+                parts.push(current);
+            }
+            parts
+        })
+        .map(|word| word.to_lowercase())
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+        .map(|word| {
+            match word.strip_suffix('s') {
+                Some(stem) if stem.len() >= 3 && !stem.ends_with('s') => stem.to_string(),
+                _ => word,
+            }
+        })
+        .filter(|word| word.len() >= 3)
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use super::{best_match, words};
+
+    #[test]
+    fn test_words_split_identifiers() {
+        let found: HashSet<String> = words("Table: InvoiceLine\n  - customer_id").collect();
+        for expected in ["table", "invoiceline", "invoice", "line", "customer"] {
+            assert!(found.contains(expected), "missing {}", expected);
+        }
+        let question: HashSet<String> = words("How many customers per invoice?").collect();
+        assert!(question.contains("customer"));
+    }
+
+    #[test]
+    fn test_best_match_prefers_schema_then_default() {
+        let scored = [("hr", 0), ("sales", 2), ("products_csv", 1)];
+        assert_eq!(best_match(&scored, Some("hr")), Some("sales"));
+
+        let tied = [("hr", 1), ("sales", 1)];
+        assert_eq!(best_match(&tied, Some("sales")), Some("sales"));
+        assert_eq!(best_match(&tied, None), Some("hr"));
+
+        let unknown = [("hr", 0), ("sales", 0)];
+        assert_eq!(best_match(&unknown, Some("sales")), Some("sales"));
+        assert_eq!(best_match(&unknown, None), None);
+        assert_eq!(best_match(&[("hr", 0)], None), Some("hr"));
+    }
+}
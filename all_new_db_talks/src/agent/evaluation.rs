@@ -0,0 +1,187 @@
+use std::{fs, time::Instant};
+
+use anyhow::{anyhow, Error};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{agent::text_to_sql::TextToSqlChain, datasource::result_set::ResultSet};
+
+/// One question of an evaluation suite
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EvalCase {
+    pub question: String,
+    /// Reference sql, its result set must match the generated one
+    #[serde(default)]
+    pub expected_sql: Option<String>,
+    /// Expected number of rows when no reference sql is given
+    #[serde(default)]
+    pub expected_rows: Option<usize>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct EvalOutcome {
+    pub question: String,
+    pub generated_sql: Option<String>,
+    pub passed: bool,
+    pub detail: String,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct EvalReport {
+    pub total: usize,
+    pub passed: usize,
+    pub outcomes: Vec<EvalOutcome>,
+}
+
+impl EvalReport {
+    pub fn accuracy(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.passed as f64 / self.total as f64
+    }
+}
+
+/// Load a suite from a JSON array or a JSON-lines file
+pub fn load_suite(path: &str) -> Result<Vec<EvalCase>, Error> {
+    let content = fs::read_to_string(path)
+        .map_err(|err| anyhow!("failed to read eval suite '{}': {}", path, err))?;
+    if content.trim_start().starts_with('[') {
+        return Ok(serde_json::from_str(&content)?);
+    }
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(i, line)| {
+            serde_json::from_str(line).map_err(|err| anyhow!("line {} of '{}': {}", i + 1, path, err))
+        })
+        .collect()
+}
+
+/// Generate sql for every case and check it against the expectation.
+/// With `dry_run` the sql is only generated, never executed.
+pub async fn run_suite(chain: &TextToSqlChain, cases: &[EvalCase], dry_run: bool) -> EvalReport {
+    let mut outcomes = Vec::new();
+    for case in cases {
+        let started = Instant::now();
+        let mut outcome = evaluate_case(chain, case, dry_run).await;
+        outcome.duration_ms = started.elapsed().as_millis() as u64;
+        outcomes.push(outcome);
+    }
+
+    EvalReport {
+        total: outcomes.len(),
+        passed: outcomes.iter().filter(|outcome| outcome.passed).count(),
+        outcomes,
+    }
+}
+
+async fn evaluate_case(chain: &TextToSqlChain, case: &EvalCase, dry_run: bool) -> EvalOutcome {
+    let mut outcome = EvalOutcome {
+        question: case.question.clone(),
+        generated_sql: None,
+        passed: false,
+        detail: String::new(),
+        duration_ms: 0,
+    };
+
+    let generated = match chain.generate_sql(case.question.clone()).await {
+        Ok(generated) => generated,
+        Err(err) => {
+            outcome.detail = format!("generation failed: {}", err);
+            return outcome;
+        }
+    };
+    outcome.generated_sql = Some(generated.sql.clone());
+
+    if dry_run {
+        outcome.passed = generated.is_executable();
+        let findings: Vec<String> = generated.findings.iter().map(|finding| finding.to_string()).collect();
+        outcome.detail = format!("generated (not executed) {}", findings.join("; "));
+        return outcome;
+    }
+
+    let rows = match chain.execute_typed(&generated).await {
+        Ok(rows) => rows,
+        Err(err) => {
+            outcome.detail = err.to_string();
+            return outcome;
+        }
+    };
+
+    match (&case.expected_sql, case.expected_rows) {
+        (Some(expected_sql), _) => match chain.execute_sql_typed(expected_sql).await {
+            Ok(expected) => {
+                outcome.passed = same_result(&rows, &expected);
+                outcome.detail = if outcome.passed {
+                    "result matches reference sql".to_string()
+                } else {
+                    format!("got {} rows, reference returned {} rows with different values", rows.len(), expected.len())
+                };
+            }
+            Err(err) => outcome.detail = format!("reference sql failed: {}", err),
+        },
+        (None, Some(expected_rows)) => {
+            outcome.passed = rows.len() == expected_rows;
+            outcome.detail = format!("got {} rows, expected {}", rows.len(), expected_rows);
+        }
+        (None, None) => {
+            outcome.passed = true;
+            outcome.detail = format!("executed, {} rows", rows.len());
+        }
+    }
+    outcome
+}
+
+/// Compare result sets column by column, ignoring column aliases and row order
+pub fn same_result(left: &ResultSet, right: &ResultSet) -> bool {
+    fn normalize(result: &ResultSet) -> Vec<Vec<String>> {
+        let mut rows: Vec<Vec<String>> = result
+            .rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|value| match value {
+                        Value::String(text) => text.clone(),
+                        value => value.to_string(),
+                    })
+                    .collect()
+            })
+            .collect();
+        rows.sort();
+        rows
+    }
+    left.columns.len() == right.columns.len() && normalize(left) == normalize(right)
+}
+
+
+#[cfg(test)]
+mod test {
+    use serde_json::{json, Value};
+
+    use super::same_result;
+    use crate::datasource::result_set::{ColumnKind, ResultColumn, ResultSet};
+
+    fn result(names: &[&str], rows: Vec<Vec<Value>>) -> ResultSet {
+        let columns = names
+            .iter()
+            .map(|name| ResultColumn { name: name.to_string(), data_type: ColumnKind::Integer })
+            .collect();
+        ResultSet { columns, rows }
+    }
+
+    #[test]
+    fn test_same_result_ignores_alias_and_order() {
+        let left = result(&["total"], vec![vec![json!(10)], vec![json!(20)]]);
+        let right = result(&["SUM(x)"], vec![vec![json!(20)], vec![json!(10)]]);
+        assert!(same_result(&left, &right));
+        assert!(!same_result(&left, &result(&["SUM(x)"], vec![vec![json!(20)]])));
+
+        // the same values in other columns are a different answer
+        let left = result(&["a", "b"], vec![vec![json!(1), json!(2)], vec![json!(2), json!(1)], vec![json!(1), json!(2)]]);
+        let right = result(&["a", "b"], vec![vec![json!(1), json!(2)], vec![json!(2), json!(1)], vec![json!(2), json!(1)]]);
+        assert!(!same_result(&left, &right));
+    }
+}
@@ -0,0 +1,141 @@
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::{agent::model_router::{unreported, ModelRouter}, configuration::model_config::ModelTask};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Intent {
+    /// Question about the data that needs SQL against the database
+    SqlQuery,
+    /// Question about which tables / columns exist
+    SchemaQuestion,
+    /// Question about the loaded CSV file
+    CsvQuery,
+    /// Greeting or anything that is not about data
+    ChitChat,
+}
+
+impl Intent {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Intent::SqlQuery => "SQL",
+            Intent::SchemaQuestion => "SCHEMA",
+            Intent::CsvQuery => "CSV",
+            Intent::ChitChat => "CHAT",
+        }
+    }
+
+    /// Pick the first known label in the model output
+    pub fn parse(output: &str) -> Option<Self> {
+        let upper = output.to_uppercase();
+        [Intent::SchemaQuestion, Intent::CsvQuery, Intent::ChitChat, Intent::SqlQuery]
+            .into_iter()
+            .filter_map(|intent| upper.find(intent.label()).map(|pos| (pos, intent)))
+            .min_by_key(|(pos, _)| *pos)
+            .map(|(_, intent)| intent)
+    }
+}
+
+pub struct IntentClassifier {
+    pub router: ModelRouter,
+}
+
+impl IntentClassifier {
+    pub fn new(router: ModelRouter) -> Self {
+        Self { router }
+    }
+
+    /// Classify the question with the intent model (TinyLlma by default).
+    /// Falls back to keyword rules when the model is unavailable or answers nonsense.
+    pub async fn classify(&self, question: &str, csv_loaded: bool) -> Result<Intent, Error> {
+        if let Some(intent) = Self::obvious_intent(question) {
+            return Ok(intent);
+        }
+
+        let prompt = Self::construct_prompt(question, csv_loaded);
+        let intent = match self.router.generate(ModelTask::IntentClassification, prompt, &unreported).await {
+            Ok(routed) => Intent::parse(&routed.response).unwrap_or(Intent::SqlQuery),
+            Err(err) => {
+                eprintln!("Intent classification failed, defaulting to SQL: {}", err);
+                Intent::SqlQuery
+            }
+        };
+
+        // never route to csv when there is nothing to query
+        if intent == Intent::CsvQuery && !csv_loaded {
+            return Ok(Intent::SqlQuery);
+        }
+        Ok(intent)
+    }
+
+    pub fn construct_prompt(question: &str, csv_loaded: bool) -> String {
+        let csv_line = if csv_loaded {
+            "- CSV: the user asks about the data in the loaded CSV file.\n"
+        } else {
+            ""
+        };
+        format!(
+            "Classify the user message into exactly one category.
+
+            Categories:
+            - SQL: the user asks a question about data stored in the database.
+            - SCHEMA: the user asks which tables, columns or fields exist.
+            {}- CHAT: greetings, thanks, or anything not about data.
+
+            User message:
+            {}
+
+            Answer with only the category name.",
+            csv_line,
+            question.trim()
+        )
+    }
+
+    /// Cheap rules for inputs that do not need a model call
+    pub fn obvious_intent(question: &str) -> Option<Intent> {
+        let normalized = question.trim().to_lowercase();
+        let normalized = normalized.trim_end_matches(['?', '!', '.']);
+        if normalized.is_empty() {
+            return Some(Intent::ChitChat);
+        }
+
+        const GREETINGS: [&str; 8] = ["hi", "hello", "hey", "thanks", "thank you", "bye", "good morning", "good evening"];
+        if GREETINGS.contains(&normalized) {
+            return Some(Intent::ChitChat);
+        }
+
+        const SCHEMA_PHRASES: [&str; 5] = [
+            "what tables",
+            "which tables",
+            "list tables",
+            "show tables",
+            "what columns",
+        ];
+        if SCHEMA_PHRASES.iter().any(|phrase| normalized.contains(phrase)) {
+            return Some(Intent::SchemaQuestion);
+        }
+
+        None
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{Intent, IntentClassifier};
+
+    #[test]
+    fn test_parse_model_output() {
+        assert_eq!(Intent::parse("SCHEMA"), Some(Intent::SchemaQuestion));
+        assert_eq!(Intent::parse("Category: chat"), Some(Intent::ChitChat));
+        assert_eq!(Intent::parse("sql"), Some(Intent::SqlQuery));
+        assert_eq!(Intent::parse("no idea"), None);
+    }
+
+    #[test]
+    fn test_obvious_intent() {
+        assert_eq!(IntentClassifier::obvious_intent("hello!"), Some(Intent::ChitChat));
+        assert_eq!(IntentClassifier::obvious_intent("What tables do you have?"), Some(Intent::SchemaQuestion));
+        assert_eq!(IntentClassifier::obvious_intent("total sales in 2012"), None);
+    }
+}
@@ -0,0 +1,177 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use utoipa::ToSchema;
+
+/// Tokens refill continuously; one question costs one token
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(capacity: u32, per_minute: u32, now: Instant) -> Self {
+        Self {
+            capacity: capacity.max(1) as f64,
+            tokens: capacity.max(1) as f64,
+            refill_per_sec: per_minute.max(1) as f64 / 60.0,
+            updated: now,
+        }
+    }
+
+    /// Take one token, or return how long until one is available
+    pub fn try_take(&mut self, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.updated = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            return Ok(());
+        }
+        Err(Duration::from_secs_f64((1.0 - self.tokens) / self.refill_per_sec))
+    }
+}
+
+/// One token bucket per api key
+pub struct RateLimiter {
+    burst: u32,
+    per_minute: u32,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(burst: u32, per_minute: u32) -> Self {
+        Self { burst, per_minute, buckets: Mutex::new(HashMap::new()) }
+    }
+
+    /// `Err` carries the time the caller should wait before retrying
+    pub fn check(&self, key: &str) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        buckets
+            .entry(key.to_string())
+            .or_insert_with(|| TokenBucket::new(self.burst, self.per_minute, now))
+            .try_take(now)
+    }
+}
+
+/// How long a request waited for a free slot
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct QueueReport {
+    pub resource: String,
+    /// Place in line when the request arrived, 1 = next
+    pub position: usize,
+    pub waited_ms: u64,
+}
+
+struct Slot {
+    semaphore: Arc<Semaphore>,
+    waiting: Arc<AtomicUsize>,
+}
+
+/// Holds a slot until dropped
+pub struct LimitPermit {
+    _permit: OwnedSemaphorePermit,
+    /// `None` when a slot was free right away
+    pub queued: Option<QueueReport>,
+}
+
+/// Keeps the waiting count right when a queued request is cancelled
+struct WaitingGuard(Arc<AtomicUsize>);
+
+impl Drop for WaitingGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// At most `limit` holders per name (datasource or model); later callers wait in line
+pub struct ConcurrencyLimiter {
+    limit: usize,
+    slots: Mutex<HashMap<String, Slot>>,
+}
+
+impl ConcurrencyLimiter {
+    pub fn new(limit: usize) -> Self {
+        Self { limit: limit.max(1), slots: Mutex::new(HashMap::new()) }
+    }
+
+    fn slot(&self, name: &str) -> (Arc<Semaphore>, Arc<AtomicUsize>) {
+        let mut slots = self.slots.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let slot = slots.entry(name.to_string()).or_insert_with(|| Slot {
+            semaphore: Arc::new(Semaphore::new(self.limit)),
+            waiting: Arc::new(AtomicUsize::new(0)),
+        });
+        (slot.semaphore.clone(), slot.waiting.clone())
+    }
+
+    /// Wait for a slot. `on_queued` gets the place in line when no slot is free right away.
+    pub async fn acquire(&self, name: &str, on_queued: impl FnOnce(usize)) -> LimitPermit {
+        let (semaphore, waiting) = self.slot(name);
+        if let Ok(permit) = semaphore.clone().try_acquire_owned() {
+            return LimitPermit { _permit: permit, queued: None };
+        }
+
+        let position = waiting.fetch_add(1, Ordering::SeqCst) + 1;
+        let _guard = WaitingGuard(waiting);
+        on_queued(position);
+        let started = Instant::now();
+        let permit = semaphore.acquire_owned().await.expect("limiter semaphores are never closed");
+        LimitPermit {
+            _permit: permit,
+            queued: Some(QueueReport {
+                resource: name.to_string(),
+                position,
+                waited_ms: started.elapsed().as_millis() as u64,
+            }),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::{ConcurrencyLimiter, TokenBucket};
+
+    #[test]
+    fn test_token_bucket_refills() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2, 60, start);
+        assert!(bucket.try_take(start).is_ok());
+        assert!(bucket.try_take(start).is_ok());
+        let retry = bucket.try_take(start).unwrap_err();
+        assert!(retry <= Duration::from_secs(1));
+        assert!(bucket.try_take(start + Duration::from_secs(1)).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_second_caller_is_queued() {
+        let limiter = ConcurrencyLimiter::new(1);
+        let first = limiter.acquire("sales", |_| {}).await;
+        assert!(first.queued.is_none());
+
+        let waiter = limiter.acquire("sales", |position| assert_eq!(position, 1));
+        tokio::pin!(waiter);
+        assert!(poll_briefly(waiter.as_mut()).await.is_none());
+        drop(first);
+        assert_eq!(waiter.await.queued.unwrap().position, 1);
+    }
+
+    async fn poll_briefly<F: std::future::Future + Unpin>(future: F) -> Option<F::Output> {
+        tokio::time::timeout(Duration::from_millis(20), future).await.ok()
+    }
+}
@@ -1 +1,12 @@
-pub mod text_to_sql;
\ No newline at end of file
+pub mod text_to_sql;
+pub mod summarizer;
+pub mod model_router;
+pub mod intent;
+pub mod clarification;
+pub mod session;
+pub mod evaluation;
+pub mod sql_validation;
+pub mod streaming;
+pub mod access_policy;
+pub mod limits;
+pub mod datasource_router;
\ No newline at end of file
@@ -0,0 +1,157 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::Error;
+use ollama_rs::{generation::completion::request::GenerationRequest, Ollama};
+use tokio_stream::StreamExt;
+
+use crate::{
+    agent::limits::{ConcurrencyLimiter, LimitPermit, QueueReport},
+    configuration::model_config::{ModelRouterConfig, ModelTask},
+    error::TalkError,
+};
+
+/// Told about every wait for a busy model, once the model is free
+pub type OnModelQueued = dyn Fn(&QueueReport) + Send + Sync;
+
+/// For callers that have nobody to tell about model waits; they are still logged
+pub fn unreported(_: &QueueReport) {}
+
+#[derive(Debug, Clone)]
+pub struct RoutedResponse {
+    pub task: ModelTask,
+    pub model: String,
+    pub response: String,
+}
+
+/// Sends each task to the model configured for its role and falls back
+/// to the next candidate when a model errors or times out.
+/// At most `model_concurrency` generations run on one model at a time.
+#[derive(Clone)]
+pub struct ModelRouter {
+    pub client: Ollama,
+    pub config: ModelRouterConfig,
+    pub limiter: Arc<ConcurrencyLimiter>,
+}
+
+impl ModelRouter {
+    pub fn new(client: Ollama, config: ModelRouterConfig, model_concurrency: usize) -> Self {
+        let limiter = Arc::new(ConcurrencyLimiter::new(model_concurrency));
+        Self { client, config, limiter }
+    }
+
+    async fn wait_for_model(&self, model: &str, on_queued: &OnModelQueued) -> LimitPermit {
+        let permit = self
+            .limiter
+            .acquire(model, |position| eprintln!("Model '{}' is busy, waiting in line ({})", model, position))
+            .await;
+        if let Some(report) = &permit.queued {
+            on_queued(report);
+        }
+        permit
+    }
+
+    pub async fn generate(&self, task: ModelTask, prompt: String, on_queued: &OnModelQueued) -> Result<RoutedResponse, Error> {
+        let role = self.config.role(self.config.role_for(task));
+        let timeout = Duration::from_secs(role.timeout_secs);
+        let mut failures = Vec::new();
+        let mut timed_out = 0;
+
+        for model in role.candidates() {
+            let request = GenerationRequest::new(model.clone(), prompt.clone());
+            let _permit = self.wait_for_model(&model, on_queued).await;
+            match tokio::time::timeout(timeout, self.client.generate(request)).await {
+                Ok(Ok(response)) => {
+                    return Ok(RoutedResponse {
+                        task,
+                        model,
+                        response: response.response,
+                    });
+                }
+                Ok(Err(err)) => {
+                    eprintln!("Model '{}' failed for {:?}: {}", model, task, err);
+                    failures.push(format!("{}: {}", model, err));
+                }
+                Err(_) => {
+                    eprintln!("Model '{}' timed out for {:?} after {:?}", model, task, timeout);
+                    failures.push(format!("{}: timed out after {:?}", model, timeout));
+                    timed_out += 1;
+                }
+            }
+        }
+
+        Err(Self::exhausted(task, &failures, timed_out).into())
+    }
+
+    /// Streaming variant of `generate`: every token is handed to `on_token` as it arrives.
+    /// Falls back to the next model only while nothing has been streamed yet.
+    pub async fn generate_streaming(
+        &self,
+        task: ModelTask,
+        prompt: String,
+        on_token: &mut (dyn FnMut(&str) + Send),
+        on_queued: &OnModelQueued,
+    ) -> Result<RoutedResponse, Error> {
+        let role = self.config.role(self.config.role_for(task));
+        let timeout = Duration::from_secs(role.timeout_secs);
+        let mut failures = Vec::new();
+        let mut timed_out = 0;
+
+        for model in role.candidates() {
+            let request = GenerationRequest::new(model.clone(), prompt.clone());
+            let _permit = self.wait_for_model(&model, on_queued).await;
+            let mut stream = match tokio::time::timeout(timeout, self.client.generate_stream(request)).await {
+                Ok(Ok(stream)) => stream,
+                Ok(Err(err)) => {
+                    eprintln!("Model '{}' failed for {:?}: {}", model, task, err);
+                    failures.push(format!("{}: {}", model, err));
+                    continue;
+                }
+                Err(_) => {
+                    eprintln!("Model '{}' timed out for {:?} after {:?}", model, task, timeout);
+                    failures.push(format!("{}: timed out after {:?}", model, timeout));
+                    timed_out += 1;
+                    continue;
+                }
+            };
+
+            let mut response = String::new();
+            loop {
+                let chunk = match tokio::time::timeout(timeout, stream.next()).await {
+                    Ok(Some(Ok(chunk))) => chunk,
+                    Ok(None) => break,
+                    Ok(Some(Err(err))) if response.is_empty() => {
+                        failures.push(format!("{}: {}", model, err));
+                        break;
+                    }
+                    Ok(Some(Err(err))) => return Err(TalkError::Llm(format!("model '{}' failed mid-stream: {}", model, err)).into()),
+                    Err(_) if response.is_empty() => {
+                        failures.push(format!("{}: timed out after {:?}", model, timeout));
+                        timed_out += 1;
+                        break;
+                    }
+                    Err(_) => return Err(TalkError::Timeout(format!("model '{}' stalled mid-stream for {:?}", model, timeout)).into()),
+                };
+                for part in chunk {
+                    on_token(&part.response);
+                    response.push_str(&part.response);
+                }
+            }
+
+            if !response.is_empty() {
+                return Ok(RoutedResponse { task, model, response });
+            }
+        }
+
+        Err(Self::exhausted(task, &failures, timed_out).into())
+    }
+
+    /// A timeout when every candidate timed out, a model failure otherwise
+    fn exhausted(task: ModelTask, failures: &[String], timed_out: usize) -> TalkError {
+        let message = format!("no model available for {:?} ({})", task, failures.join("; "));
+        if timed_out > 0 && timed_out == failures.len() {
+            TalkError::Timeout(message)
+        } else {
+            TalkError::Llm(message)
+        }
+    }
+}
@@ -0,0 +1,129 @@
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Rough budget for the history block in the sql prompt, in tokens
+pub const DEFAULT_HISTORY_TOKEN_BUDGET: usize = 800;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, ToSchema)]
+pub struct ConversationTurn {
+    pub question: String,
+    pub sql: Option<String>,
+    pub summary: Option<String>,
+    /// How many sql generations were needed for this turn
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+    #[serde(default)]
+    pub result_preview: Option<String>,
+    /// Free text or rating given by the user afterwards
+    #[serde(default)]
+    pub feedback: Option<String>,
+}
+
+impl ConversationTurn {
+    fn render(&self, index: usize) -> String {
+        let mut text = format!("Q{}: {}\n", index, self.question.trim());
+        if let Some(sql) = &self.sql {
+            text.push_str(&format!("SQL{}: {}\n", index, sql.trim()));
+        }
+        if let Some(summary) = &self.summary {
+            text.push_str(&format!("Answer{}: {}\n", index, summary.trim()));
+        }
+        text
+    }
+}
+
+/// Prior questions, sql and answers so follow-ups can refine the previous query
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct ConversationSession {
+    pub id: String,
+    pub turns: Vec<ConversationTurn>,
+    pub token_budget: usize,
+    /// Name of the api key that started the session; no other key may read or continue it
+    #[serde(skip)]
+    pub owner: Option<String>,
+}
+
+impl Default for ConversationSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConversationSession {
+    /// Starts with a random 128 bit id, so ids can be neither guessed nor repeated
+    pub fn new() -> Self {
+        let mut bytes = [0u8; 16];
+        rand::rng().fill_bytes(&mut bytes);
+        Self {
+            id: bytes.iter().map(|byte| format!("{:02x}", byte)).collect(),
+            turns: Vec::new(),
+            token_budget: DEFAULT_HISTORY_TOKEN_BUDGET,
+            owner: None,
+        }
+    }
+
+    pub fn owned_by(owner: Option<&str>) -> Self {
+        Self { owner: owner.map(str::to_string), ..Self::new() }
+    }
+
+    pub fn record(&mut self, question: &str, sql: Option<String>, summary: Option<String>) {
+        let attempts = if sql.is_some() { 1 } else { 0 };
+        self.turns.push(ConversationTurn {
+            question: question.trim().to_string(),
+            sql,
+            summary,
+            attempts,
+            ..Default::default()
+        });
+    }
+
+    pub fn last_turn_mut(&mut self) -> Option<&mut ConversationTurn> {
+        self.turns.last_mut()
+    }
+
+    pub fn last_sql(&self) -> Option<&str> {
+        self.turns.iter().rev().find_map(|turn| turn.sql.as_deref())
+    }
+
+    /// Most recent turns that fit in the token budget, oldest first.
+    /// Tokens are estimated as 4 characters each.
+    pub fn history_prompt(&self) -> String {
+        let budget_chars = self.token_budget * 4;
+        let mut used = 0;
+        let mut blocks = Vec::new();
+
+        for (i, turn) in self.turns.iter().enumerate().rev() {
+            let block = turn.render(i + 1);
+            if used + block.len() > budget_chars {
+                break;
+            }
+            used += block.len();
+            blocks.push(block);
+        }
+
+        blocks.reverse();
+        blocks.concat()
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::ConversationSession;
+
+    #[test]
+    fn test_history_respects_budget() {
+        let mut session = ConversationSession::new();
+        session.token_budget = 10;
+        session.record("total sales per year", Some("SELECT 1".to_string()), None);
+        session.record("now only for 2012", Some("SELECT 2".to_string()), None);
+
+        let history = session.history_prompt();
+        assert!(history.contains("now only for 2012"));
+        assert!(!history.contains("total sales per year"));
+        assert_eq!(session.last_sql(), Some("SELECT 2"));
+    }
+}
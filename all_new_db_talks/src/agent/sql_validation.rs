@@ -0,0 +1,425 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::datasource::db_utill::DatabaseSchema;
+
+/// Statements that change data or structure; generated sql must never contain them.
+/// REPLACE is left out because it is also a string function.
+const WRITE_KEYWORDS: [&str; 10] = [
+    "insert", "update", "delete", "drop", "alter", "truncate", "create", "grant", "revoke", "rename",
+];
+const READ_STARTS: [&str; 5] = ["select", "with", "show", "describe", "explain"];
+/// Keywords that end the table list of a FROM clause
+const FROM_CLAUSE_END: [&str; 16] = [
+    "where", "group", "having", "order", "limit", "offset", "fetch", "union", "except", "intersect", "window",
+    "qualify", "for", "into", "lock", "procedure",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    /// The sql must not be executed
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ValidationFinding {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self.severity {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "[{}] {}", label, self.message)
+    }
+}
+
+/// SQL produced by the model, not yet executed
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct GeneratedSql {
+    pub question: String,
+    pub sql: String,
+    pub findings: Vec<ValidationFinding>,
+}
+
+impl GeneratedSql {
+    pub fn is_executable(&self) -> bool {
+        !self.findings.iter().any(|finding| finding.severity == Severity::Error)
+    }
+}
+
+pub struct SqlValidator;
+
+impl SqlValidator {
+    /// Static checks on a single read-only statement. `schema` enables the unknown-table check.
+    pub fn validate(sql: &str, schema: Option<&DatabaseSchema>) -> Vec<ValidationFinding> {
+        let mut findings = Vec::new();
+        let mut push = |severity, message: String| findings.push(ValidationFinding { severity, message });
+
+        let tokens = Self::statement_tokens(sql);
+        if tokens.is_empty() {
+            push(Severity::Error, "no SQL was generated".to_string());
+            return findings;
+        }
+
+        let first = tokens.first().map(String::as_str).unwrap_or_default();
+        if !READ_STARTS.contains(&first) {
+            push(
+                Severity::Error,
+                format!("statement starts with '{}', expected a read-only query (SELECT / WITH)", first),
+            );
+        }
+
+        if tokens.iter().any(|token| token == ";") {
+            push(Severity::Error, "multiple statements are not allowed".to_string());
+        }
+
+        for keyword in WRITE_KEYWORDS {
+            if tokens.iter().any(|token| token == keyword) {
+                push(Severity::Error, format!("'{}' would modify the database", keyword.to_uppercase()));
+            }
+        }
+
+        if let Some(target) = Self::file_target(&tokens) {
+            push(Severity::Error, format!("'INTO {}' would write a file on the database server", target.to_uppercase()));
+        }
+
+        if tokens.windows(2).any(|pair| pair[0] == "select" && pair[1] == "*") {
+            push(Severity::Info, "SELECT * returns every column, consider naming the columns".to_string());
+        }
+
+        if first == "select" && !tokens.iter().any(|token| token == "limit") && !Self::is_aggregate_only(&tokens) {
+            push(Severity::Warning, "no LIMIT clause, the result may be large".to_string());
+        }
+
+        if let Some(schema) = schema {
+            let known: Vec<String> = schema.schemas.iter().map(|table| table.table_name.to_lowercase()).collect();
+            let ctes = Self::cte_names(&tokens);
+            match Self::referenced_tables(&tokens) {
+                Ok(tables) => {
+                    for table in tables {
+                        if !known.contains(&table) && !ctes.contains(&table) {
+                            push(Severity::Warning, format!("table '{}' does not exist in the schema", table));
+                        }
+                    }
+                }
+                Err(reason) => push(Severity::Warning, reason),
+            }
+        }
+
+        findings
+    }
+
+    /// Tables read by the statement (lowercased), CTE names excluded.
+    /// `Err` when some FROM item is not a plain table and the list would be incomplete.
+    pub fn tables_in(sql: &str) -> Result<Vec<String>, String> {
+        let tokens = Self::statement_tokens(sql);
+        let ctes = Self::cte_names(&tokens);
+        Ok(Self::referenced_tables(&tokens)?
+            .into_iter()
+            .filter(|table| !ctes.contains(table))
+            .collect())
+    }
+
+    /// True for SELECT and WITH statements, the ones whose tables `tables_in` can list;
+    /// SHOW, DESCRIBE and EXPLAIN name their table without a FROM
+    pub fn is_query(sql: &str) -> bool {
+        Self::statement_tokens(sql).first().is_some_and(|first| first == "select" || first == "with")
+    }
+
+    /// True when the statement contains a data or structure changing keyword, or writes a file
+    pub fn is_write(sql: &str) -> bool {
+        let tokens = Self::statement_tokens(sql);
+        tokens.iter().any(|token| WRITE_KEYWORDS.contains(&token.as_str())) || Self::file_target(&tokens).is_some()
+    }
+
+    /// `outfile` or `dumpfile` for `SELECT ... INTO OUTFILE '/path'`, which reads like a query
+    fn file_target(tokens: &[String]) -> Option<&str> {
+        tokens
+            .windows(2)
+            .find(|pair| pair[0] == "into" && (pair[1] == "outfile" || pair[1] == "dumpfile"))
+            .map(|pair| pair[1].as_str())
+    }
+
+    /// Tokens of one statement, without the semicolons that end it
+    fn statement_tokens(sql: &str) -> Vec<String> {
+        let mut tokens = Self::tokenize(sql);
+        while tokens.last().is_some_and(|token| token == ";") {
+            tokens.pop();
+        }
+        tokens
+    }
+
+    /// Lowercased words. String literals become `''` and comments are dropped, so
+    /// neither can fake or hide a keyword; quoted identifiers are one token without
+    /// their quotes. Parentheses, commas and semicolons are separate tokens.
+    /// The body of a MySQL `/*! ... */` comment is kept, because the server runs it.
+    fn tokenize(sql: &str) -> Vec<String> {
+        let chars: Vec<char> = sql.chars().collect();
+        let mut tokens = Vec::new();
+        let mut word = String::new();
+        let flush = |word: &mut String, tokens: &mut Vec<String>| {
+            if !word.is_empty() {
+                tokens.push(word.to_lowercase());
+                word.clear();
+            }
+        };
+        let mut in_executable_comment = false;
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let next = chars.get(i + 1).copied();
+            match c {
+                '\'' => {
+                    flush(&mut word, &mut tokens);
+                    i += 1;
+                    while i < chars.len() {
+                        match chars[i] {
+                            '\\' => i += 1,
+                            '\'' if chars.get(i + 1) == Some(&'\'') => i += 1,
+                            '\'' => break,
+                            _ => {}
+                        }
+                        i += 1;
+                    }
+                    tokens.push("''".to_string());
+                }
+                '`' | '"' => {
+                    flush(&mut word, &mut tokens);
+                    let mut name = String::new();
+                    i += 1;
+                    while i < chars.len() {
+                        if chars[i] == c {
+                            if chars.get(i + 1) != Some(&c) {
+                                break;
+                            }
+                            i += 1;
+                        }
+                        name.push(chars[i]);
+                        i += 1;
+                    }
+                    tokens.push(name.to_lowercase());
+                }
+                // `--` starts a comment only when followed by whitespace, as in MySQL
+                '-' if next == Some('-') && chars.get(i + 2).is_none_or(|after| after.is_whitespace()) => {
+                    flush(&mut word, &mut tokens);
+                    while i < chars.len() && chars[i] != '\n' {
+                        i += 1;
+                    }
+                }
+                '#' => {
+                    flush(&mut word, &mut tokens);
+                    while i < chars.len() && chars[i] != '\n' {
+                        i += 1;
+                    }
+                }
+                '/' if next == Some('*') && chars.get(i + 2) == Some(&'!') => {
+                    flush(&mut word, &mut tokens);
+                    in_executable_comment = true;
+                    // `/*!50700` carries the server version the body needs
+                    i += 3;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                    continue;
+                }
+                '/' if next == Some('*') => {
+                    flush(&mut word, &mut tokens);
+                    i += 2;
+                    while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                '*' if next == Some('/') && in_executable_comment => {
+                    flush(&mut word, &mut tokens);
+                    in_executable_comment = false;
+                    i += 1;
+                }
+                '(' | ')' | ',' | ';' => {
+                    flush(&mut word, &mut tokens);
+                    tokens.push(c.to_string());
+                }
+                c if c.is_whitespace() => flush(&mut word, &mut tokens),
+                _ => word.push(c),
+            }
+            i += 1;
+        }
+        flush(&mut word, &mut tokens);
+        tokens
+    }
+
+    /// Every table named in a FROM clause: comma lists, joins, parenthesized joins
+    /// and the FROMs of subqueries. `Err` names the first item that is not a plain
+    /// table, such as a table function, so callers can refuse what they cannot check.
+    fn referenced_tables(tokens: &[String]) -> Result<Vec<String>, String> {
+        let mut tables = Vec::new();
+        for (i, token) in tokens.iter().enumerate() {
+            if token == "from" && Self::is_from_clause(tokens, i) {
+                Self::from_list(tokens, i + 1, &mut tables)?;
+            }
+        }
+        Ok(tables)
+    }
+
+    /// False for the FROM of `EXTRACT(YEAR FROM d)`, `TRIM(x FROM s)` and the like:
+    /// a clause FROM is top level or inside parentheses that open a query
+    fn is_from_clause(tokens: &[String], from: usize) -> bool {
+        let mut depth = 0;
+        for i in (0..from).rev() {
+            match tokens[i].as_str() {
+                ")" => depth += 1,
+                "(" if depth == 0 => {
+                    return tokens.get(i + 1).is_some_and(|next| next == "select" || next == "with");
+                }
+                "(" => depth -= 1,
+                _ => {}
+            }
+        }
+        true
+    }
+
+    /// Collect the table references starting at `start` up to the end of the clause;
+    /// returns the index where the clause ended
+    fn from_list(tokens: &[String], start: usize, tables: &mut Vec<String>) -> Result<usize, String> {
+        let mut expect_table = true;
+        let mut depth = 0;
+        let mut i = start;
+        while i < tokens.len() {
+            let token = tokens[i].as_str();
+            if depth > 0 {
+                match token {
+                    "(" => depth += 1,
+                    ")" => depth -= 1,
+                    _ => {}
+                }
+            } else if token == ")" || token == ";" || FROM_CLAUSE_END.contains(&token) {
+                break;
+            } else if expect_table {
+                expect_table = false;
+                match token {
+                    // derived table; its own FROM is read when the outer scan reaches it
+                    "(" if tokens.get(i + 1).is_some_and(|next| next == "select" || next == "with") => depth += 1,
+                    // parenthesized join
+                    "(" => i = Self::from_list(tokens, i + 1, tables)?,
+                    "lateral" => expect_table = true,
+                    name if Self::is_plain_name(name) && tokens.get(i + 1).is_none_or(|next| next != "(") => {
+                        // db.table -> table
+                        let name = name.rsplit('.').next().unwrap_or(name).to_string();
+                        if name != "dual" && !tables.contains(&name) {
+                            tables.push(name);
+                        }
+                    }
+                    other => return Err(format!("cannot tell which table '{}' reads", other)),
+                }
+            } else {
+                match token {
+                    "," | "join" | "straight_join" => expect_table = true,
+                    // ON (...), USING (...), USE INDEX (...)
+                    "(" => depth += 1,
+                    _ => {}
+                }
+            }
+            i += 1;
+        }
+        if expect_table {
+            return Err("a FROM clause names no table".to_string());
+        }
+        Ok(i)
+    }
+
+    /// A space can only come from a quoted identifier, such as `play list`
+    fn is_plain_name(token: &str) -> bool {
+        token.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+            && token.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$' || c == '.' || c == ' ')
+            && !FROM_CLAUSE_END.contains(&token)
+            && !READ_STARTS.contains(&token)
+    }
+
+    /// Names defined as `name AS (` in a WITH clause
+    fn cte_names(tokens: &[String]) -> Vec<String> {
+        tokens
+            .windows(3)
+            .filter(|window| window[1] == "as" && window[2] == "(")
+            .map(|window| window[0].clone())
+            .collect()
+    }
+
+    /// `SELECT COUNT(*) FROM ...` without GROUP BY returns a single row
+    fn is_aggregate_only(tokens: &[String]) -> bool {
+        let aggregates = ["count", "sum", "avg", "min", "max"];
+        tokens.get(1).is_some_and(|token| aggregates.contains(&token.as_str()))
+            && !tokens.iter().any(|token| token == "group")
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::datasource::db_utill::{DatabaseSchema, TableSchema};
+
+    use super::{Severity, SqlValidator};
+
+    fn schema() -> DatabaseSchema {
+        DatabaseSchema {
+            schemas: vec![TableSchema {
+                table_name: "Invoice".to_string(),
+                field_columns: Vec::new(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_write_statement_is_rejected() {
+        let findings = SqlValidator::validate("DELETE FROM Invoice", Some(&schema()));
+        assert!(findings.iter().any(|f| f.severity == Severity::Error));
+    }
+
+    #[test]
+    fn test_unknown_table_is_reported() {
+        let findings = SqlValidator::validate("SELECT Total FROM Invoices LIMIT 5", Some(&schema()));
+        assert!(findings.iter().any(|f| f.message.contains("'invoices'")));
+        assert!(findings.iter().all(|f| f.severity != Severity::Error));
+    }
+
+    #[test]
+    fn test_clean_query_has_no_findings() {
+        let sql = "WITH t AS (SELECT Total FROM `Invoice`) SELECT COUNT(*) FROM t";
+        assert!(SqlValidator::validate(sql, Some(&schema())).is_empty());
+    }
+
+    #[test]
+    fn test_literals_and_comments_are_not_sql() {
+        let errors = |sql: &str| {
+            SqlValidator::validate(sql, None).into_iter().filter(|f| f.severity == Severity::Error).count()
+        };
+        assert_eq!(errors("SELECT Name FROM Track WHERE Name = 'Update' LIMIT 5;"), 0);
+        assert_eq!(errors("SELECT Name FROM Track WHERE Name = ';' OR Name = 'it''s; drop' LIMIT 5"), 0);
+        assert_eq!(errors("SELECT Name FROM Track -- delete later\n LIMIT 5"), 0);
+        assert_eq!(errors("SELECT 1; DROP TABLE Track"), 2);
+        assert!(!SqlValidator::is_write("SELECT Name FROM Track /* insert */ WHERE Name = 'Create'"));
+        assert!(SqlValidator::is_write("SELECT 1 /*!50000 ; DROP TABLE Track */"));
+        assert_eq!(errors("SELECT * FROM Track INTO OUTFILE '/tmp/track.csv'"), 1);
+        assert!(SqlValidator::is_write("SELECT Name INTO DUMPFILE '/tmp/x' FROM Track"));
+        assert_eq!(SqlValidator::tables_in("SELECT * FROM `Play list`, Track WHERE x = 'a from b'").unwrap(), ["play list", "track"]);
+    }
+
+    #[test]
+    fn test_tables_of_comma_joins_and_subqueries() {
+        let tables = |sql: &str| SqlValidator::tables_in(sql).unwrap();
+        assert_eq!(tables("SELECT * FROM Invoice, Employee"), ["invoice", "employee"]);
+        assert_eq!(tables("SELECT * FROM a x, b AS y JOIN c ON x.id = c.id, d"), ["a", "b", "c", "d"]);
+        assert_eq!(tables("SELECT * FROM (a JOIN b USING (id)), (SELECT 1 FROM c) t"), ["a", "b", "c"]);
+        assert_eq!(tables("SELECT EXTRACT(YEAR FROM InvoiceDate) FROM Invoice LIMIT 1"), ["invoice"]);
+        assert!(SqlValidator::tables_in("SELECT * FROM Invoice, JSON_TABLE('[]', '$[*]' COLUMNS (x INT PATH '$')) j").is_err());
+    }
+}
@@ -0,0 +1,119 @@
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::mpsc::UnboundedSender;
+use utoipa::ToSchema;
+
+use crate::{
+    agent::{limits::QueueReport, sql_validation::GeneratedSql, summarizer::AnswerSummarizer, text_to_sql::TextToSqlChain},
+    datasource::result_set::ResultColumn,
+};
+
+/// Result rows are sent in chunks of this size
+pub const ROW_CHUNK_SIZE: usize = 50;
+
+/// Progress of one streamed question, in the order the events are sent
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum ChainEvent {
+    /// Every slot of the datasource is busy and the run starts when this request reaches the front,
+    /// or the run waited for a busy model (sent once the model is free)
+    Queued { resource: String, position: usize },
+    SqlToken(String),
+    Validated(GeneratedSql),
+    Executing,
+    Columns(Vec<ResultColumn>),
+    Rows(#[schema(value_type = Vec<Vec<Object>>)] Vec<Vec<Value>>),
+    SummaryToken(String),
+    Done { sql: String, row_count: usize, summary: String },
+    Error(String),
+}
+
+impl ChainEvent {
+    /// Event name used for the SSE `event:` field
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChainEvent::Queued { .. } => "queued",
+            ChainEvent::SqlToken(_) => "sql_token",
+            ChainEvent::Validated(_) => "validated",
+            ChainEvent::Executing => "executing",
+            ChainEvent::Columns(_) => "columns",
+            ChainEvent::Rows(_) => "rows",
+            ChainEvent::SummaryToken(_) => "summary_token",
+            ChainEvent::Done { .. } => "done",
+            ChainEvent::Error(_) => "error",
+        }
+    }
+}
+
+/// Checked after validation and before execution, e.g. the caller's access policy
+pub type SqlGuard = dyn Fn(&GeneratedSql) -> Result<(), String> + Send + Sync;
+
+/// Run generate -> validate -> execute -> summarize, reporting every step on `events`.
+/// The run ends with either `Done` or `Error`; it stops early once the receiver is gone.
+pub async fn stream_ask(
+    chain: &TextToSqlChain,
+    question: String,
+    history: &str,
+    guard: &SqlGuard,
+    events: UnboundedSender<ChainEvent>,
+) {
+    if let Err(message) = run_stream(chain, question, history, guard, &events).await {
+        let _ = events.send(ChainEvent::Error(message));
+    }
+}
+
+async fn run_stream(
+    chain: &TextToSqlChain,
+    question: String,
+    history: &str,
+    guard: &SqlGuard,
+    events: &UnboundedSender<ChainEvent>,
+) -> Result<(), String> {
+    let queue_events = events.clone();
+    let on_model_queued = move |report: &QueueReport| {
+        let _ = queue_events.send(ChainEvent::Queued { resource: report.resource.clone(), position: report.position });
+    };
+    let sql_events = events.clone();
+    let mut on_sql_token = move |token: &str| {
+        let _ = sql_events.send(ChainEvent::SqlToken(token.to_string()));
+    };
+    let generated = chain
+        .generate_sql_streaming(question, history, &mut on_sql_token, &on_model_queued)
+        .await
+        .map_err(|err| err.to_string())?;
+    send(events, ChainEvent::Validated(generated.clone()))?;
+    if !generated.is_executable() {
+        return Err("the generated SQL failed validation and was not executed".to_string());
+    }
+    guard(&generated)?;
+
+    send(events, ChainEvent::Executing)?;
+    let result = chain.execute_typed(&generated).await.map_err(|err| err.to_string())?;
+    send(events, ChainEvent::Columns(result.columns.clone()))?;
+    for chunk in result.rows.chunks(ROW_CHUNK_SIZE) {
+        send(events, ChainEvent::Rows(chunk.to_vec()))?;
+    }
+
+    let summary_events = events.clone();
+    let mut on_summary_token = move |token: &str| {
+        let _ = summary_events.send(ChainEvent::SummaryToken(token.to_string()));
+    };
+    let summarizer = AnswerSummarizer::new(chain.router.clone());
+    let answer = summarizer
+        .summarize_streaming(&generated.question, &generated.sql, result.to_string_rows(), &mut on_summary_token, &on_model_queued)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    send(
+        events,
+        ChainEvent::Done {
+            sql: answer.sql,
+            row_count: result.len(),
+            summary: answer.narrative,
+        },
+    )
+}
+
+fn send(events: &UnboundedSender<ChainEvent>, event: ChainEvent) -> Result<(), String> {
+    events.send(event).map_err(|_| "client disconnected".to_string())
+}
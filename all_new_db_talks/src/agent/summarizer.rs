@@ -0,0 +1,155 @@
+use std::collections::HashMap;
+
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::{agent::model_router::{ModelRouter, OnModelQueued}, configuration::model_config::ModelTask};
+
+/// How many rows of the result set are shown to the model.
+pub const MAX_PREVIEW_ROWS: usize = 20;
+/// Hard cap on the characters of a single cell in the preview.
+pub const MAX_CELL_CHARS: usize = 80;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SummarizedAnswer {
+    pub question: String,
+    pub sql: String,
+    pub narrative: String,
+    pub data: Vec<HashMap<String, String>>,
+}
+
+pub struct AnswerSummarizer {
+    pub router: ModelRouter,
+}
+
+impl AnswerSummarizer {
+    pub fn new(router: ModelRouter) -> Self {
+        Self { router }
+    }
+
+    /// Ask the summarization model (NplOperate by default) to explain the result set in plain language
+    pub async fn summarize(
+        &self,
+        question: &str,
+        sql: &str,
+        data: Vec<HashMap<String, String>>,
+        on_queued: &OnModelQueued,
+    ) -> Result<SummarizedAnswer, Error> {
+        let prompt = Self::construct_prompt(question, sql, &data);
+        let response = self.router.generate(ModelTask::Summarization, prompt, on_queued).await?;
+
+        Ok(SummarizedAnswer {
+            question: question.trim().to_string(),
+            sql: sql.to_string(),
+            narrative: response.response.trim().to_string(),
+            data,
+        })
+    }
+
+    /// Same as `summarize`, handing narrative tokens to `on_token` while they are generated
+    pub async fn summarize_streaming(
+        &self,
+        question: &str,
+        sql: &str,
+        data: Vec<HashMap<String, String>>,
+        on_token: &mut (dyn FnMut(&str) + Send),
+        on_queued: &OnModelQueued,
+    ) -> Result<SummarizedAnswer, Error> {
+        let prompt = Self::construct_prompt(question, sql, &data);
+        let response = self.router.generate_streaming(ModelTask::Summarization, prompt, on_token, on_queued).await?;
+
+        Ok(SummarizedAnswer {
+            question: question.trim().to_string(),
+            sql: sql.to_string(),
+            narrative: response.response.trim().to_string(),
+            data,
+        })
+    }
+
+    pub fn construct_prompt(question: &str, sql: &str, data: &[HashMap<String, String>]) -> String {
+        format!(
+            "You are a data analyst explaining query results to a business user.
+
+            User Question:
+            {}
+
+            SQL that was executed:
+            {}
+
+            Result preview ({} of {} rows):
+            {}
+
+            Instructions:
+            - Answer the user question in plain language, in a few sentences.
+            - Quote the key numbers from the result exactly as they appear.
+            - If the preview is partial, say that more rows exist.
+            - If the result is empty, say that no matching data was found.
+            - Do not output SQL and do not invent values that are not in the result.",
+            question.trim(),
+            sql,
+            data.len().min(MAX_PREVIEW_ROWS),
+            data.len(),
+            Self::preview_rows(data)
+        )
+    }
+
+    /// Render the first rows as a pipe separated table with stable column order
+    pub fn preview_rows(data: &[HashMap<String, String>]) -> String {
+        let Some(first) = data.first() else {
+            return "(no rows)".to_string();
+        };
+        let mut headers: Vec<&String> = first.keys().collect();
+        headers.sort();
+
+        let mut output = String::new();
+        output.push_str(&headers.iter().map(|h| h.as_str()).collect::<Vec<_>>().join(" | "));
+        output.push('\n');
+
+        for row in data.iter().take(MAX_PREVIEW_ROWS) {
+            let cells: Vec<String> = headers
+                .iter()
+                .map(|h| {
+                    let value = row.get(*h).map(String::as_str).unwrap_or("NULL");
+                    Self::truncate_cell(value)
+                })
+                .collect();
+            output.push_str(&cells.join(" | "));
+            output.push('\n');
+        }
+
+        output
+    }
+
+    fn truncate_cell(value: &str) -> String {
+        if value.chars().count() <= MAX_CELL_CHARS {
+            return value.to_string();
+        }
+        let cut: String = value.chars().take(MAX_CELL_CHARS).collect();
+        format!("{}...", cut)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::{AnswerSummarizer, MAX_PREVIEW_ROWS};
+
+    #[test]
+    fn test_preview_is_bounded() {
+        let data: Vec<HashMap<String, String>> = (0..50)
+            .map(|i| HashMap::from([("total".to_string(), i.to_string())]))
+            .collect();
+        let preview = AnswerSummarizer::preview_rows(&data);
+
+        // header line + bounded rows
+        assert_eq!(preview.lines().count(), MAX_PREVIEW_ROWS + 1);
+        assert!(preview.starts_with("total"));
+    }
+
+    #[test]
+    fn test_preview_empty() {
+        assert_eq!(AnswerSummarizer::preview_rows(&[]), "(no rows)");
+    }
+}
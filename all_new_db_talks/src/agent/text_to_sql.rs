@@ -1,80 +1,455 @@
-use std::env;
+use std::{collections::HashMap, fmt, time::Instant};
 
-use anyhow::Error;
-use crate::{configuration::model_config::ModelSelect, datasource::async_db_utill::AsyncDb};
-use ollama_rs::{generation::completion::request::GenerationRequest, Ollama};
-use sqlx::mysql::MySqlPool;
+use anyhow::{anyhow, Error};
 use async_trait::async_trait;
-use crate::{datasource::db_utill::{DatabaseSchema, DbUtil}, trait_req_impl::chain::Chain};
-use dotenv::dotenv;
-use anyhow::anyhow;
+use ollama_rs::Ollama;
+use serde::Serialize;
+
+use crate::{
+    agent::{
+        clarification::{ClarificationDetector, ClarificationRequest},
+        intent::{Intent, IntentClassifier},
+        model_router::{unreported, ModelRouter, OnModelQueued},
+        session::ConversationSession,
+        sql_validation::{GeneratedSql, SqlValidator},
+        summarizer::{AnswerSummarizer, SummarizedAnswer, MAX_PREVIEW_ROWS},
+    },
+    configuration::{
+        app_config::AppConfig,
+        csv_config::CsvOptions,
+        datasource_config::{DatasourceConfig, DatasourceKind, DEFAULT_DATASOURCE},
+        model_config::ModelTask,
+        secret::Secret,
+    },
+    datasource::{
+        csv_utill::CsvUtill,
+        db_utill::DatabaseSchema,
+        pool::{PoolHealth, SharedPool},
+        result_set::ResultSet,
+    },
+    error::TalkError,
+    trait_req_impl::{chain::Chain, csv_trait::CsvImplTrait},
+};
+
 pub struct TextToSqlChain{
     pub client: Ollama,
-    pub db: MySqlPool
+    pub router: ModelRouter,
+    /// Shared by introspection and execution; `None` for a csv datasource,
+    /// which answers every question from `csv`
+    pub pool: Option<SharedPool>,
+    pub csv: Option<CsvUtill>,
+    pub clarify: bool,
+    /// Keep at most this many rows of every executed query
+    pub row_limit: Option<usize>,
+    /// Carries the password, see `Secret`
+    pub db_url: Secret
+}
+
+/// What the chain produced for one input, depending on the classified intent
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum ChainResponse {
+    Answer(SummarizedAnswer),
+    Schema(DatabaseSchema),
+    Csv { sql: String, output: String },
+    Clarification(ClarificationRequest),
+    Reply(String),
+}
+
+impl fmt::Display for ChainResponse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainResponse::Answer(answer) => {
+                writeln!(f, "SQL: {}", answer.sql)?;
+                writeln!(f, "{}", answer.narrative)?;
+                write!(f, "({} rows returned)", answer.data.len())
+            }
+            ChainResponse::Schema(schema) => write!(f, "{}", schema),
+            ChainResponse::Csv { sql, output } => {
+                writeln!(f, "SQL: {}", sql)?;
+                write!(f, "{}", output)
+            }
+            ChainResponse::Clarification(request) => {
+                writeln!(f, "Your question can be read in more than one way: {}", request.reason)?;
+                for (i, option) in request.options.iter().enumerate() {
+                    writeln!(f, "  {}. {}", i + 1, option)?;
+                }
+                write!(f, "Which one did you mean?")
+            }
+            ChainResponse::Reply(reply) => write!(f, "{}", reply),
+        }
+    }
 }
 
 
 #[async_trait]
 impl Chain for TextToSqlChain {
-    async fn initialze() -> Result<Box<dyn Chain + Send>, Error>
+    async fn initialze(config: &AppConfig) -> Result<Box<dyn Chain + Send>, TalkError>
         where
             Self: Sized
     {
-        let _ = dotenv().ok();
-        let db_url = env::var("DATABASE_URL").expect("Failed to load Database url, please review .env");
-        let ollama_host = env::var("OLAMA_URL").expect("can't connect to ollama, please review .env file");
-        let ollama_port = env::var("OLAMA_PORT").expect("Failed to retrive port information, please review .env");
-        let pool = MySqlPool::connect(&db_url).await?;
-        let ollama = Ollama::new(&ollama_host, ollama_port.parse().unwrap());
-        Ok(
-            Box::new(TextToSqlChain {
-                client: ollama,
-                db: pool
-            })
-        )
-    }
-
-
-    async fn run(&self, input: String) -> Result<String, Error>{
-        let prompt = self.construct_prompt(input).await?;
-        let request = GenerationRequest::new(
-            ModelSelect::SqlOperate.as_str(),
-            prompt
+        Ok(Box::new(TextToSqlChain::connect(config).await?))
+    }
+
+
+    async fn run(&self, input: String) -> Result<String, TalkError>{
+        let response = self.respond(input).await?;
+        Ok(response.to_string())
+    }
+}
+
+
+impl TextToSqlChain {
+    /// Connect to `[database] url` with an already validated configuration
+    pub async fn connect(config: &AppConfig) -> Result<Self, Error> {
+        let ollama = Ollama::new(&config.llm.url, config.llm.port);
+        let router = ModelRouter::new(ollama.clone(), config.models.clone(), config.limits.model_concurrency);
+        let profile = DatasourceConfig::new(&config.database.db_url, &config.database.options);
+        Self::connect_datasource(config, DEFAULT_DATASOURCE, &profile, ollama, router).await
+    }
+
+    /// A chain over one csv file alone; no database is configured or contacted
+    pub async fn connect_csv(config: &AppConfig, file: &str, options: CsvOptions) -> Result<Self, Error> {
+        let profile = DatasourceConfig { kind: DatasourceKind::Csv, path: file.to_string(), csv: options, ..DatasourceConfig::default() };
+        profile.validate("csv")?;
+        let ollama = Ollama::new(&config.llm.url, config.llm.port);
+        let router = ModelRouter::new(ollama.clone(), config.models.clone(), config.limits.model_concurrency);
+        Self::connect_datasource(config, "csv", &profile, ollama, router).await
+    }
+
+    /// Connect to one named datasource. Chains of one deployment share `router`,
+    /// so model concurrency stays bounded across datasources.
+    pub async fn connect_datasource(config: &AppConfig, name: &str, profile: &DatasourceConfig, client: Ollama, router: ModelRouter) -> Result<Self, Error> {
+        let (pool, csv, db_url) = if profile.is_csv() {
+            let csv = CsvUtill::with_options(profile.path.clone(), profile.csv.clone());
+            if !csv.verify_path() {
+                return Err(anyhow!("csv file '{}' does not exist", profile.path));
+            }
+            (None, Some(csv), Secret::default())
+        } else {
+            let (db_url, options) = profile.resolve(name)?;
+            let pool = SharedPool::connect(db_url.clone(), options)
+                .await
+                .map_err(|err| TalkError::Connection(db_url.scrub(&err.to_string())))?;
+            (Some(pool), None, db_url)
+        };
+        Ok(TextToSqlChain {
+            client,
+            router,
+            pool,
+            csv,
+            clarify: config.chain.clarify,
+            row_limit: profile.row_limit.or(config.chain.row_limit),
+            db_url
+        })
+    }
+
+    /// The datasource's shared pool
+    pub fn pool(&self) -> Result<&SharedPool, Error> {
+        self.require_database()?;
+        self.pool.as_ref().ok_or_else(|| anyhow!("datasource has no database pool"))
+    }
+
+    /// `None` for csv datasources, which have no pool
+    pub async fn health(&self) -> Option<PoolHealth> {
+        match &self.pool {
+            Some(pool) => Some(pool.check().await),
+            None => None,
+        }
+    }
+
+    pub fn is_csv_only(&self) -> bool {
+        self.pool.is_none()
+    }
+
+    /// Csv datasources have no pool; their sql runs on the csv file instead
+    fn require_database(&self) -> Result<(), Error> {
+        if self.is_csv_only() {
+            return Err(anyhow!("this datasource is a csv file and has no database connection"));
+        }
+        Ok(())
+    }
+
+    /// Make a csv file available for questions classified as csv queries
+    pub fn attach_csv(&mut self, csv: CsvUtill) {
+        self.csv = Some(csv);
+    }
+
+    pub fn detach_csv(&mut self) {
+        self.csv = None;
+    }
+
+    /// Classify the input and answer it with sql, the schema, the csv file or a plain reply
+    pub async fn respond(&self, input: String) -> Result<ChainResponse, Error> {
+        self.respond_with_history(input, "").await
+    }
+
+    /// Same as `respond`, but follow-ups can refine earlier questions of the session.
+    /// The turn is recorded in the session afterwards.
+    pub async fn respond_in_session(&self, session: &mut ConversationSession, input: String) -> Result<ChainResponse, Error> {
+        let started = Instant::now();
+        let history = session.history_prompt();
+        let response = self.respond_with_history(input.clone(), &history).await?;
+        Self::record_turn(session, &input, &response, started);
+        Ok(response)
+    }
+
+    pub async fn resolve_clarification_in_session(&self, session: &mut ConversationSession, request: &ClarificationRequest, choice: &str) -> Result<ChainResponse, Error> {
+        let question = request.resolve(choice)?;
+        let started = Instant::now();
+        let history = session.history_prompt();
+        let response = ChainResponse::Answer(self.ask_with_history(question.clone(), &history).await?);
+        Self::record_turn(session, &question, &response, started);
+        Ok(response)
+    }
+
+    /// Append the answered turn with its timing and a result preview to the session
+    pub fn record_turn(session: &mut ConversationSession, input: &str, response: &ChainResponse, started: Instant) {
+        let preview = match response {
+            ChainResponse::Answer(answer) => {
+                session.record(input, Some(answer.sql.clone()), Some(answer.narrative.clone()));
+                AnswerSummarizer::preview_rows(&answer.data)
+            }
+            ChainResponse::Csv { sql, output } => {
+                session.record(input, Some(sql.clone()), None);
+                output.lines().take(MAX_PREVIEW_ROWS + 2).collect::<Vec<_>>().join("\n")
+            }
+            // clarifications are recorded once resolved, chit-chat and schema add nothing to refine
+            ChainResponse::Clarification(_) | ChainResponse::Reply(_) | ChainResponse::Schema(_) => return,
+        };
+        if let Some(turn) = session.last_turn_mut() {
+            turn.duration_ms = Some(started.elapsed().as_millis() as u64);
+            turn.result_preview = Some(preview);
+        }
+    }
+
+    async fn respond_with_history(&self, input: String, history: &str) -> Result<ChainResponse, Error> {
+        let classifier = IntentClassifier::new(self.router.clone());
+        let intent = match classifier.classify(&input, self.csv.is_some()).await? {
+            Intent::SqlQuery if self.is_csv_only() => Intent::CsvQuery,
+            intent => intent,
+        };
+
+        match intent {
+            Intent::SqlQuery => {
+                if let Some(request) = self.needs_clarification(&input, history).await {
+                    return Ok(ChainResponse::Clarification(request));
+                }
+                Ok(ChainResponse::Answer(self.ask_with_history(input, history).await?))
+            }
+            Intent::SchemaQuestion => Ok(ChainResponse::Schema(self.get_db_info().await?)),
+            Intent::CsvQuery => self.ask_csv(input).await,
+            Intent::ChitChat => Ok(ChainResponse::Reply(
+                "Hi! I can answer questions about the data in the connected database. Try asking something like \"how many invoices were issued in 2012?\" or \"what tables do you have?\"".to_string()
+            )),
+        }
+    }
+
+    /// Answer a question the chain asked to clarify, using the user's choice
+    pub async fn resolve_clarification(&self, request: &ClarificationRequest, choice: &str) -> Result<ChainResponse, Error> {
+        let question = request.resolve(choice)?;
+        Ok(ChainResponse::Answer(self.ask(question).await?))
+    }
+
+    /// A failing check never blocks the question, it just falls through to sql generation
+    async fn needs_clarification(&self, input: &str, history: &str) -> Option<ClarificationRequest> {
+        if !self.clarify {
+            return None;
+        }
+        let schema = self.get_db_info().await.ok()?;
+        let detector = ClarificationDetector::new(self.router.clone());
+        match detector.check(input, &schema.to_string(), history).await {
+            Ok(request) => request,
+            Err(err) => {
+                eprintln!("Clarification check failed: {}", err);
+                None
+            }
+        }
+    }
+
+    pub async fn ask_csv(&self, input: String) -> Result<ChainResponse, Error> {
+        let clean_query = self.generate_csv_sql(input, &unreported).await?;
+        let csv = self.attached_csv()?;
+        let output = CsvUtill::record_batches_to_string(csv.execute_csv_query(clean_query.clone()).await?);
+        Ok(ChainResponse::Csv { sql: clean_query, output })
+    }
+
+    fn attached_csv(&self) -> Result<&CsvUtill, Error> {
+        self.csv.as_ref().ok_or_else(|| anyhow!("no csv file is attached"))
+    }
+
+    pub async fn generate_csv_sql(&self, input: String, on_queued: &OnModelQueued) -> Result<String, Error> {
+        let csv = self.attached_csv()?;
+        let columns = csv.get_columns().await?;
+        let prompt = format!(
+            "You are a data expert.
+
+            Table: {}
+            Columns: {}
+
+            Instructions:
+            - Generate ONE SQL query (DataFusion / PostgreSQL dialect) that answers the user question.
+            - Only output the SQL command, no explanations and no markdown.
+
+            User Question:
+            {}",
+            csv.table_name(),
+            columns.join(", "),
+            input.trim()
         );
-        let sql = self.client.generate(request).await.unwrap();
-        println!("SQL is {:?}", sql.response);
-        let clean_query = sql.response
+        let sql = self.router.generate(ModelTask::SqlGeneration, prompt, on_queued).await?;
+        Ok(Self::extract_sql(&sql.response)?)
+    }
+
+    /// Generate sql without executing it, together with validation findings
+    pub async fn generate_sql(&self, input: String) -> Result<GeneratedSql, Error> {
+        self.generate_sql_with_history(input, "", &unreported).await
+    }
+
+    /// `on_queued` hears about every wait for a busy model
+    pub async fn generate_sql_with_history(&self, input: String, history: &str, on_queued: &OnModelQueued) -> Result<GeneratedSql, Error> {
+        if self.is_csv_only() {
+            let sql = self.generate_csv_sql(input.clone(), on_queued).await?;
+            return Ok(self.validate_sql(input, sql).await);
+        }
+        let prompt = self.construct_prompt_with_history(input.clone(), history).await?;
+        let sql = self.router.generate(ModelTask::SqlGeneration, prompt, on_queued).await?;
+        Ok(self.validate_sql(input, Self::extract_sql(&sql.response)?).await)
+    }
+
+    /// Same as `generate_sql_with_history`, handing sql tokens to `on_token` as the model writes them
+    pub async fn generate_sql_streaming(&self, input: String, history: &str, on_token: &mut (dyn FnMut(&str) + Send), on_queued: &OnModelQueued) -> Result<GeneratedSql, Error> {
+        if self.is_csv_only() {
+            let generated = self.generate_sql_with_history(input, history, on_queued).await?;
+            on_token(&generated.sql);
+            return Ok(generated);
+        }
+        let prompt = self.construct_prompt_with_history(input.clone(), history).await?;
+        let sql = self.router.generate_streaming(ModelTask::SqlGeneration, prompt, on_token, on_queued).await?;
+        Ok(self.validate_sql(input, Self::extract_sql(&sql.response)?).await)
+    }
+
+    pub async fn generate_sql_in_session(&self, session: &ConversationSession, input: String, on_queued: &OnModelQueued) -> Result<GeneratedSql, Error> {
+        self.generate_sql_with_history(input, &session.history_prompt(), on_queued).await
+    }
+
+    /// Validate sql against the current schema, e.g. after the user edited it
+    pub async fn validate_sql(&self, question: String, sql: String) -> GeneratedSql {
+        let schema = self.get_db_info().await.ok();
+        let findings = SqlValidator::validate(&sql, schema.as_ref());
+        GeneratedSql { question, sql, findings }
+    }
+
+    pub fn clean_sql(raw: &str) -> String {
+        raw
         .replace("```sql", "")
         .replace("```", "")
         .trim()
-        .to_string();
-        
-        let asy_db = AsyncDb::new().unwrap();
-        let query_data = asy_db.query_as_string(clean_query).await.unwrap();
-        Ok(query_data)
+        .to_string()
     }
-}
 
+    /// `clean_sql`, failing when the model's answer holds no sql at all
+    pub fn extract_sql(raw: &str) -> Result<String, TalkError> {
+        let sql = Self::clean_sql(raw);
+        if sql.is_empty() {
+            return Err(TalkError::Extraction("the model answered without any sql".to_string()));
+        }
+        Ok(sql)
+    }
 
-impl TextToSqlChain {
-    pub async fn get_db_info(&self) -> Result<DatabaseSchema, Error> {
-        let tool = match DbUtil::new() {
-            Ok(mut data) => {
-                let result = data.get_database_schema();
+    /// Generate and execute the SQL, then let the NplOperate model explain the result
+    pub async fn ask(&self, input: String) -> Result<SummarizedAnswer, Error> {
+        self.ask_with_history(input, "").await
+    }
+
+    pub async fn ask_with_history(&self, input: String, history: &str) -> Result<SummarizedAnswer, Error> {
+        let generated = self.generate_sql_with_history(input, history, &unreported).await?;
+        self.answer_generated(&generated).await
+    }
+
+    /// Execute already generated (and possibly user edited) sql and explain the result
+    pub async fn answer_generated(&self, generated: &GeneratedSql) -> Result<SummarizedAnswer, Error> {
+        let rows = self.execute_generated(generated).await?;
+
+        let summarizer = AnswerSummarizer::new(self.router.clone());
+        summarizer.summarize(&generated.question, &generated.sql, rows, &unreported).await
+    }
+
+    pub async fn answer_generated_in_session(&self, session: &mut ConversationSession, generated: &GeneratedSql) -> Result<ChainResponse, Error> {
+        let started = Instant::now();
+        let response = ChainResponse::Answer(self.answer_generated(generated).await?);
+        Self::record_turn(session, &generated.question, &response, started);
+        Ok(response)
+    }
+
+    /// Refuses sql with error findings, so unsafe statements never reach the database
+    pub async fn execute_generated(&self, generated: &GeneratedSql) -> Result<Vec<HashMap<String, String>>, Error> {
+        if !generated.is_executable() {
+            return Err(TalkError::Validation(generated.findings.clone()).into());
+        }
+        self.execute_sql(&generated.sql).await
+    }
+
+    /// Same guard as `execute_generated`, returning typed columns
+    pub async fn execute_typed(&self, generated: &GeneratedSql) -> Result<ResultSet, Error> {
+        if !generated.is_executable() {
+            return Err(TalkError::Validation(generated.findings.clone()).into());
+        }
+        self.execute_sql_typed(&generated.sql).await
+    }
+
+    /// Run sql as given with typed columns, honouring `row_limit`, e.g. the reference sql of an eval case
+    pub async fn execute_sql_typed(&self, sql: &str) -> Result<ResultSet, Error> {
+        let limit = self.row_limit;
+        match self.is_csv_only() {
+            true => {
+                let mut result = self.attached_csv()?.execute_csv_typed(sql.to_string()).await?;
+                if let Some(limit) = limit {
+                    result.truncate(limit);
+                }
                 Ok(result)
-            },
-            Err(_) => Err(anyhow!("fail to get database schema")),
-        };
+            }
+            false => Ok(self.pool()?.run(|db| async move { db.query_typed(sql, limit).await }).await
+                .map_err(TalkError::from)?),
+        }
+    }
+
+    /// Run sql against the database, honouring `row_limit`; database rows past the limit are never fetched
+    pub async fn execute_sql(&self, sql: &str) -> Result<Vec<HashMap<String, String>>, Error> {
+        let limit = self.row_limit;
+        match self.is_csv_only() {
+            true => {
+                let mut rows = self.attached_csv()?.execute_csv_typed(sql.to_string()).await?.to_string_rows();
+                if let Some(limit) = limit {
+                    rows.truncate(limit);
+                }
+                Ok(rows)
+            }
+            false => Ok(self.pool()?.run(|db| async move { db.query(sql, limit).await }).await
+                .map_err(TalkError::from)?),
+        }
+    }
 
-        tool
+    pub async fn get_db_info(&self) -> Result<DatabaseSchema, Error> {
+        if self.is_csv_only() {
+            return Ok(DatabaseSchema { schemas: vec![self.attached_csv()?.table_schema().await?] });
+        }
+        self.pool()?
+            .run(|db| async move { db.database_schema().await })
+            .await
+            .map_err(|err| anyhow!("fail to get database schema: {}", err))
     }
 
     pub async fn construct_prompt(&self, input:String) -> Result<String, Error> {
-        let mut db = match DbUtil::new() {
-            Ok(tool) => tool,
+        self.construct_prompt_with_history(input, "").await
+    }
+
+    pub async fn construct_prompt_with_history(&self, input:String, history: &str) -> Result<String, Error> {
+        self.require_database()?;
+        let db_schema = match self.get_db_info().await {
+            Ok(schema) => schema,
             Err(_) => return Err(anyhow!("Failed to retive database schema"))
         };
-        let db_schema = db.get_database_schema();
 
         let prompt = format!(
             "You are a database expert.
@@ -88,12 +463,17 @@ impl TextToSqlChain {
             - No explanations, no examples, no prefixes (such as 'Example:', 'SQL:', 'Response:', 'Result:').
             - No formatting like markdown (no ```sql blocks).
             - Output ONLY the SQL query — no extra text.
+            - If the question refers to the previous conversation (\"now only for 2012\", \"sort that by total\"), modify the most recent SQL accordingly.
+            
+            Previous Conversation:
+            {}
             
             User Question:
             {}
             
             Remember: ONLY output a single valid SQL command.",
             db_schema,
+            if history.is_empty() { "(none)" } else { history },
             input.trim()
         );
     
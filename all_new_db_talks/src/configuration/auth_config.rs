@@ -0,0 +1,42 @@
+use std::fs;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::agent::access_policy::ApiKeyPolicy;
+
+/// `[auth]` in the config file, overlaid by `API_KEYS_FILE` / `API_AUTH_DISABLED`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    /// Keys inline in the config file, checked before `keys_file` and the SQLite store
+    pub keys: Vec<ApiKeyPolicy>,
+    /// Json array of more keys
+    pub keys_file: Option<String>,
+    /// Lets every request through, for local development only
+    pub disabled: bool,
+}
+
+impl AuthConfig {
+    pub fn overlay_env(&mut self, env: &dyn Fn(&str) -> Option<String>) {
+        if let Some(path) = env("API_KEYS_FILE") {
+            self.keys_file = Some(path);
+        }
+        if let Some(value) = env("API_AUTH_DISABLED") {
+            self.disabled = matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes");
+        }
+    }
+
+    /// Inline keys followed by the ones in `keys_file`
+    pub fn load_keys(&self) -> Result<Vec<ApiKeyPolicy>> {
+        let mut keys = self.keys.clone();
+        if let Some(path) = &self.keys_file {
+            let content = fs::read_to_string(path)
+                .map_err(|err| anyhow!("failed to read keys file '{}': {}", path, err))?;
+            let file_keys: Vec<ApiKeyPolicy> = serde_json::from_str(&content)
+                .map_err(|err| anyhow!("keys file '{}' is not a json array of keys: {}", path, err))?;
+            keys.extend(file_keys);
+        }
+        Ok(keys)
+    }
+}
@@ -0,0 +1,134 @@
+use std::{fmt, str::FromStr};
+
+use crate::configuration::secret::{lookup_secret, Secret};
+
+/// One missing or invalid setting
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigProblem {
+    pub key: String,
+    pub message: String,
+    /// How to fix it
+    pub hint: String,
+}
+
+/// Every configuration problem found while loading, reported together
+/// so a broken setup can be fixed in one pass
+#[derive(Debug, Clone, Default)]
+pub struct ConfigError {
+    pub problems: Vec<ConfigProblem>,
+}
+
+impl ConfigError {
+    pub fn single(key: &str, message: impl Into<String>, hint: impl Into<String>) -> Self {
+        let mut error = Self::default();
+        error.push(key, message, hint);
+        error
+    }
+
+    pub fn push(&mut self, key: &str, message: impl Into<String>, hint: impl Into<String>) {
+        self.problems.push(ConfigProblem {
+            key: key.to_string(),
+            message: message.into(),
+            hint: hint.into(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    /// Collect the problems of a nested step
+    pub fn absorb<T>(&mut self, result: Result<T, ConfigError>) -> Option<T> {
+        match result {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.problems.extend(error.problems);
+                None
+            }
+        }
+    }
+
+    /// `Ok(value)` when nothing was recorded
+    pub fn finish<T>(self, value: T) -> Result<T, ConfigError> {
+        if self.is_empty() { Ok(value) } else { Err(self) }
+    }
+
+    /// Read a key that must be present and non-empty
+    pub fn required(&mut self, env: &dyn Fn(&str) -> Option<String>, key: &str, hint: &str) -> Option<String> {
+        match env(key).filter(|value| !value.trim().is_empty()) {
+            Some(value) => Some(value),
+            None => {
+                self.push(key, "is not set", hint);
+                None
+            }
+        }
+    }
+
+    /// Parse a value, recording a problem naming the `expected` form when it does not parse
+    pub fn parse<T: FromStr>(&mut self, key: &str, value: &str, expected: &str) -> Option<T> {
+        match value.trim().parse() {
+            Ok(parsed) => Some(parsed),
+            Err(_) => {
+                self.push(key, format!("'{}' is not {}", value, expected), format!("set {} to {}", key, expected));
+                None
+            }
+        }
+    }
+
+    /// `required` followed by `parse`
+    pub fn required_parse<T: FromStr>(
+        &mut self,
+        env: &dyn Fn(&str) -> Option<String>,
+        key: &str,
+        expected: &str,
+    ) -> Option<T> {
+        let value = self.required(env, key, &format!("set {} to {}", key, expected))?;
+        self.parse(key, &value, expected)
+    }
+
+    /// Read a secret through `<key>_FILE` or `<key>` (see `lookup_secret`); absent keys are fine
+    pub fn secret(&mut self, env: &dyn Fn(&str) -> Option<String>, key: &str) -> Option<Secret> {
+        match lookup_secret(env, key) {
+            Ok(secret) => secret,
+            Err(message) => {
+                self.push(key, message, format!("set either {} or {}_FILE, and check the file or variable it names", key, key));
+                None
+            }
+        }
+    }
+
+    /// A secret that must be present; an empty value is allowed
+    pub fn required_secret(&mut self, env: &dyn Fn(&str) -> Option<String>, key: &str, hint: &str) -> Option<Secret> {
+        match lookup_secret(env, key) {
+            Ok(Some(secret)) => Some(secret),
+            Ok(None) => {
+                self.push(key, "is not set", hint);
+                None
+            }
+            Err(_) => self.secret(env, key),
+        }
+    }
+
+    /// Parse an optional key; absent keys are fine
+    pub fn optional_parse<T: FromStr>(
+        &mut self,
+        env: &dyn Fn(&str) -> Option<String>,
+        key: &str,
+        expected: &str,
+    ) -> Option<T> {
+        let value = env(key)?;
+        self.parse(key, &value, expected)
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid configuration ({} problem(s)):", self.problems.len())?;
+        for problem in &self.problems {
+            write!(f, "\n  - {}: {}\n    hint: {}", problem.key, problem.message, problem.hint)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
@@ -0,0 +1,182 @@
+use std::path::Path;
+
+use clap::{Args, ValueEnum};
+use serde::{Deserialize, Serialize};
+
+use crate::configuration::config_error::ConfigError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum CsvEncoding {
+    #[serde(alias = "utf-8")]
+    Utf8,
+    /// ISO-8859-1, also fine for plain ascii
+    #[serde(alias = "latin-1", alias = "iso-8859-1")]
+    Latin1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum CsvCompression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl CsvCompression {
+    /// `.gz` and `.zst` files are compressed, anything else is read as is
+    pub fn from_path(path: &str) -> Self {
+        let lower = path.to_ascii_lowercase();
+        if lower.ends_with(".gz") || lower.ends_with(".gzip") {
+            CsvCompression::Gzip
+        } else if lower.ends_with(".zst") || lower.ends_with(".zstd") {
+            CsvCompression::Zstd
+        } else {
+            CsvCompression::None
+        }
+    }
+}
+
+/// How to read a csv file. Unset fields fall back to a comma separated, double quoted,
+/// utf-8 file with a header row, compression taken from the extension.
+/// In the config file these sit next to `path` in a `kind = "csv"` datasource.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Args)]
+#[serde(default)]
+pub struct CsvOptions {
+    /// Table name questions are answered against (default: the file name, e.g. sales_2024 for sales-2024.csv.gz)
+    #[arg(long = "table")]
+    pub table_name: Option<String>,
+    #[arg(long)]
+    pub delimiter: Option<char>,
+    #[arg(long)]
+    pub quote: Option<char>,
+    /// Escape character inside quoted values (default: quotes are escaped by doubling them)
+    #[arg(long)]
+    pub escape: Option<char>,
+    /// Whether the first line names the columns (default: true)
+    #[arg(long)]
+    pub has_header: Option<bool>,
+    #[arg(long, value_enum)]
+    pub encoding: Option<CsvEncoding>,
+    /// Values read as NULL, e.g. NA or an empty string
+    #[arg(long = "null", value_delimiter = ',')]
+    pub null_values: Vec<String>,
+    #[arg(long, value_enum)]
+    pub compression: Option<CsvCompression>,
+}
+
+impl CsvOptions {
+    pub fn delimiter(&self) -> u8 {
+        self.delimiter.map_or(b',', |c| c as u8)
+    }
+
+    pub fn quote(&self) -> u8 {
+        self.quote.map_or(b'"', |c| c as u8)
+    }
+
+    pub fn escape(&self) -> Option<u8> {
+        self.escape.map(|c| c as u8)
+    }
+
+    pub fn has_header(&self) -> bool {
+        self.has_header.unwrap_or(true)
+    }
+
+    pub fn encoding(&self) -> CsvEncoding {
+        self.encoding.unwrap_or(CsvEncoding::Utf8)
+    }
+
+    pub fn compression(&self, path: &str) -> CsvCompression {
+        self.compression.unwrap_or_else(|| CsvCompression::from_path(path))
+    }
+
+    /// `table_name`, else one derived from the file name
+    pub fn table_name(&self, path: &str) -> String {
+        self.table_name.clone().unwrap_or_else(|| table_name_from_path(path))
+    }
+
+    /// The null tokens as one anchored regex, as DataFusion takes them
+    pub fn null_regex(&self) -> Option<String> {
+        if self.null_values.is_empty() {
+            return None;
+        }
+        let tokens: Vec<String> = self.null_values.iter().map(|token| escape_regex(token)).collect();
+        Some(format!("^({})$", tokens.join("|")))
+    }
+
+    pub fn validate(&self, key: &str) -> Result<(), ConfigError> {
+        let mut error = ConfigError::default();
+        for (name, value) in [("delimiter", self.delimiter), ("quote", self.quote), ("escape", self.escape)] {
+            if value.is_some_and(|c| !c.is_ascii() || c == '\n' || c == '\r') {
+                error.push(&format!("{}.{}", key, name), "must be a single ascii character", "e.g. \";\" or \"\\t\"");
+            }
+        }
+        if self.delimiter.is_some() && self.delimiter == self.quote {
+            error.push(&format!("{}.quote", key), "is the same character as the delimiter", "pick another quote character");
+        }
+        if let Some(name) = &self.table_name {
+            if !is_identifier(name) {
+                error.push(&format!("{}.table_name", key), format!("'{}' is not a plain sql name", name), "use letters, digits and _, not starting with a digit");
+            }
+        }
+        let compressed = self.compression.is_some_and(|compression| compression != CsvCompression::None);
+        if compressed && self.encoding() != CsvEncoding::Utf8 {
+            error.push(&format!("{}.encoding", key), "only utf8 is supported for compressed files", "decompress the file or convert it to utf-8");
+        }
+        error.finish(())
+    }
+}
+
+/// `sales-2024.csv.gz` -> `sales_2024`: the name up to the first dot, lowercased,
+/// anything but letters, digits and _ replaced
+pub fn table_name_from_path(path: &str) -> String {
+    let file_name = Path::new(path).file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+    let stem = file_name.split('.').next().unwrap_or_default();
+    let name: String = stem
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+    let name = name.trim_matches('_');
+    match name.chars().next() {
+        None => "csv".to_string(),
+        Some(first) if first.is_ascii_digit() => format!("t_{}", name),
+        Some(_) => name.to_string(),
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn escape_regex(token: &str) -> String {
+    let mut escaped = String::with_capacity(token.len());
+    for c in token.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{table_name_from_path, CsvCompression, CsvOptions};
+
+    #[test]
+    fn test_names_and_null_tokens() {
+        assert_eq!(table_name_from_path("/data/Sales-2024.csv.gz"), "sales_2024");
+        assert_eq!(table_name_from_path("2024 orders.csv"), "t_2024_orders");
+        assert_eq!(CsvCompression::from_path("dump.csv.zst"), CsvCompression::Zstd);
+
+        let options = CsvOptions { null_values: vec!["NA".into(), "".into(), "n/a?".into()], ..Default::default() };
+        assert_eq!(options.null_regex().unwrap(), "^(NA||n/a\\?)$");
+        assert_eq!(options.table_name("products-100.csv"), "products_100");
+
+        let broken = CsvOptions { delimiter: Some('é'), table_name: Some("1st".into()), ..Default::default() };
+        assert_eq!(broken.validate("csv").unwrap_err().problems.len(), 2);
+    }
+}
@@ -0,0 +1,141 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::configuration::{
+    config_error::ConfigError,
+    csv_config::CsvOptions,
+    secret::Secret,
+    load_config::{connection_options::ConnectionOptions, db_config_factory::DbConfig},
+};
+
+/// Name of the datasource built from `[database] url` / `DATABASE_URL`
+pub const DEFAULT_DATASOURCE: &str = "default";
+
+const URL_HINT: &str = "set url, or the *_USERNAME, *_HOST ... env values of its kind";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DatasourceKind {
+    #[default]
+    Mysql,
+    Mariadb,
+    /// Known from the `POSTGRES_*` env values, but not queryable yet: validation rejects it
+    Postgres,
+    /// Not queryable yet, like `Postgres`
+    Sqlite,
+    /// A csv file queried through DataFusion
+    Csv,
+}
+
+impl DatasourceKind {
+    /// Driver config read from the `*_USERNAME`, `*_HOST` ... env values; `None` for csv
+    pub fn db_config(&self) -> Option<DbConfig> {
+        match self {
+            DatasourceKind::Mysql => Some(DbConfig::MYSQL),
+            DatasourceKind::Mariadb => Some(DbConfig::MARIADB),
+            DatasourceKind::Postgres => Some(DbConfig::POSTGRES),
+            DatasourceKind::Sqlite => Some(DbConfig::SQLITE),
+            DatasourceKind::Csv => None,
+        }
+    }
+
+    /// Whether questions can be answered from this kind; execution runs on a MySQL pool or DataFusion
+    pub fn is_supported(&self) -> bool {
+        matches!(self, DatasourceKind::Mysql | DatasourceKind::Mariadb | DatasourceKind::Csv)
+    }
+
+    pub fn check_supported(&self, key: &str) -> Result<(), ConfigError> {
+        if self.is_supported() {
+            return Ok(());
+        }
+        let name = self.to_possible_value().map(|value| value.get_name().to_string()).unwrap_or_default();
+        Err(ConfigError::single(
+            key,
+            format!("'{}' datasources are not supported yet", name),
+            "use mysql, mariadb or csv",
+        ))
+    }
+}
+
+/// One `[datasources.<name>]` profile, e.g.
+/// `[datasources.sales] kind = "mysql", url = "..."` or
+/// `[datasources.products_csv] kind = "csv", path = "products.csv"`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DatasourceConfig {
+    pub kind: DatasourceKind,
+    /// Connection url, or an `env:NAME` / `file:PATH` reference to it;
+    /// when empty `AppConfig::load` builds it from the env values of `kind`
+    pub url: Secret,
+    /// Csv file, for `kind = "csv"`
+    pub path: String,
+    /// What the data is about, used with the schema to route questions
+    pub description: String,
+    /// Overrides `[chain] row_limit` for this datasource
+    pub row_limit: Option<usize>,
+    /// `ssl_mode`, `ssl_ca`, `socket`, `pool_size` ... next to `url`
+    #[serde(flatten)]
+    pub options: ConnectionOptions,
+    /// `table_name`, `delimiter`, `null_values` ... next to `path`
+    #[serde(flatten)]
+    pub csv: CsvOptions,
+}
+
+impl DatasourceConfig {
+    pub fn new(url: &Secret, options: &ConnectionOptions) -> Self {
+        Self { url: url.clone(), options: options.clone(), ..Self::default() }
+    }
+
+    pub fn is_csv(&self) -> bool {
+        self.kind == DatasourceKind::Csv
+    }
+
+    /// The url and options to connect with
+    pub fn resolve(&self, name: &str) -> Result<(Secret, ConnectionOptions), ConfigError> {
+        if self.is_csv() {
+            return Err(ConfigError::single(
+                &format!("datasources.{}.url", name),
+                "a csv datasource has no url",
+                "set path instead",
+            ));
+        }
+        if self.url.is_blank() {
+            return Err(ConfigError::single(&format!("datasources.{}.url", name), "is empty", URL_HINT));
+        }
+        Ok((self.url.clone(), self.options.clone()))
+    }
+
+    pub fn validate(&self, name: &str) -> Result<(), ConfigError> {
+        let mut error = ConfigError::default();
+        if name.trim().is_empty() || name.contains(',') {
+            error.push("datasources", format!("'{}' is not a usable name", name), "use letters, digits and _");
+        }
+        error.absorb(self.kind.check_supported(&format!("datasources.{}.kind", name)));
+        if self.kind.is_supported() && !self.is_csv() && self.url.is_blank() {
+            error.push(&format!("datasources.{}.url", name), "is empty", URL_HINT);
+        }
+        if self.is_csv() && self.path.trim().is_empty() {
+            error.push(
+                &format!("datasources.{}.path", name),
+                "is empty",
+                "point it at the csv file, e.g. path = \"products.csv\"",
+            );
+        }
+        if self.row_limit == Some(0) {
+            error.push(&format!("datasources.{}.row_limit", name), "is 0", "remove it or use a number above 0");
+        }
+        error.absorb(self.options.validate(&format!("datasources.{}", name)));
+        if self.is_csv() && !self.options.set_names().is_empty() {
+            error.push(
+                &format!("datasources.{}", name),
+                format!("{} do(es) not apply to a csv datasource", self.options.set_names().join(", ")),
+                "remove them, only path, row_limit, description and the csv options are read",
+            );
+        }
+        if self.is_csv() {
+            error.absorb(self.csv.validate(&format!("datasources.{}", name)));
+        }
+        error.finish(())
+    }
+}
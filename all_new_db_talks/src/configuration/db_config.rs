@@ -1,17 +1,25 @@
-use std::env;
+use serde::{Deserialize, Serialize};
 
-use dotenv::dotenv;
+use crate::configuration::{config_error::ConfigError, load_config::connection_options::ConnectionOptions, secret::Secret};
 
+/// `[database]` in the config file, overlaid by `DATABASE_URL` or `DATABASE_URL_FILE`
+/// and `DATABASE_SSL_MODE`, `DATABASE_POOL_SIZE` ...
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DatabaseConfig{
-    pub db_url: String
+    #[serde(rename = "url")]
+    pub db_url: Secret,
+    #[serde(flatten)]
+    pub options: ConnectionOptions,
 }
 
 impl DatabaseConfig {
-    pub fn inject_from_env() -> Self {
-        dotenv().ok();
-        let db_url = env::var("DATABASE_URL").expect("Fail to retrive DATABASE_URL from env file, please reive");
-        Self {
-            db_url
+    pub fn overlay_env(&mut self, env: &dyn Fn(&str) -> Option<String>) -> Result<(), ConfigError> {
+        let mut error = ConfigError::default();
+        if let Some(db_url) = error.secret(env, "DATABASE_URL") {
+            self.db_url = db_url;
         }
+        error.absorb(self.options.overlay_env(env, "DATABASE"));
+        error.finish(())
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+use crate::configuration::config_error::ConfigError;
+
+/// `[limits]` in the config file, overlaid by `RATE_LIMIT_*` and `*_MAX_CONCURRENCY`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LimitsConfig {
+    /// Sustained questions per minute allowed for one api key
+    pub requests_per_minute: u32,
+    /// Questions a key may send at once before the per-minute rate applies
+    pub burst: u32,
+    /// Requests running against one datasource at the same time; the rest wait in line
+    pub datasource_concurrency: usize,
+    /// Generations running on one model at the same time
+    pub model_concurrency: usize,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_minute: 30,
+            burst: 10,
+            datasource_concurrency: 4,
+            model_concurrency: 2,
+        }
+    }
+}
+
+impl LimitsConfig {
+    pub fn overlay_env(&mut self, env: &dyn Fn(&str) -> Option<String>) -> Result<(), ConfigError> {
+        let mut error = ConfigError::default();
+        let expected = "a whole number above 0";
+        if let Some(value) = error.optional_parse(env, "RATE_LIMIT_PER_MINUTE", expected) {
+            self.requests_per_minute = value;
+        }
+        if let Some(value) = error.optional_parse(env, "RATE_LIMIT_BURST", expected) {
+            self.burst = value;
+        }
+        if let Some(value) = error.optional_parse(env, "DATASOURCE_MAX_CONCURRENCY", expected) {
+            self.datasource_concurrency = value;
+        }
+        if let Some(value) = error.optional_parse(env, "MODEL_MAX_CONCURRENCY", expected) {
+            self.model_concurrency = value;
+        }
+        error.finish(())
+    }
+
+    /// Zero would block every request
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut error = ConfigError::default();
+        let values = [
+            ("limits.requests_per_minute", self.requests_per_minute as usize),
+            ("limits.burst", self.burst as usize),
+            ("limits.datasource_concurrency", self.datasource_concurrency),
+            ("limits.model_concurrency", self.model_concurrency),
+        ];
+        for (key, value) in values {
+            if value == 0 {
+                error.push(key, "is 0, nothing could run", "use a number above 0");
+            }
+        }
+        error.finish(())
+    }
+}
@@ -1,21 +1,44 @@
-use std::env;
+use serde::{Deserialize, Serialize};
 
+use crate::configuration::config_error::ConfigError;
+
+/// `[llm]` in the config file, overlaid by `OLAMA_URL` / `OLAMA_PORT`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct LLMConfig{
     pub url: String,
     pub port: u16
 }
 
+impl Default for LLMConfig {
+    fn default() -> Self {
+        Self { url: "http://localhost".to_string(), port: 11434 }
+    }
+}
+
 impl LLMConfig{
-    pub fn inject_from_env() -> Self {
-        dotenv::dotenv().ok();
-        let ollama_url = match env::var("OLAMA_URL") {
-            Ok(data) => data,
-            Err(_) => "".to_string()
-        };
-        let ollama_port: u16 = match env::var("OLAMA_PORT") {
-            Ok(data) => data.parse().unwrap(),
-            Err(_) => 0
-        };
-        Self { url: ollama_url, port: ollama_port }
+    pub fn overlay_env(&mut self, env: &dyn Fn(&str) -> Option<String>) -> Result<(), ConfigError> {
+        let mut error = ConfigError::default();
+        if let Some(url) = env("OLAMA_URL") {
+            self.url = url;
+        }
+        if let Some(port) = error.optional_parse(env, "OLAMA_PORT", "a port number (e.g. 11434)") {
+            self.port = port;
+        }
+        error.finish(())
     }
-}
\ No newline at end of file
+
+    /// An empty url or port 0 can never reach Ollama
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut error = ConfigError::default();
+        if self.url.trim().is_empty() {
+            error.push("llm.url", "is empty", "set [llm] url or OLAMA_URL, e.g. http://localhost");
+        } else if !self.url.starts_with("http://") && !self.url.starts_with("https://") {
+            error.push("llm.url", format!("'{}' has no http:// or https:// scheme", self.url), "write it as http://host");
+        }
+        if self.port == 0 {
+            error.push("llm.port", "is 0", "set [llm] port or OLAMA_PORT, Ollama listens on 11434 by default");
+        }
+        error.finish(())
+    }
+}
@@ -0,0 +1,234 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::configuration::{config_error::ConfigError, secret::encode_component};
+
+/// MySQL's names; postgres uses the matching `disable` ... `verify-full`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SslMode {
+    #[serde(alias = "disable")]
+    Disabled,
+    #[serde(alias = "prefer")]
+    Preferred,
+    #[serde(alias = "require")]
+    Required,
+    #[serde(alias = "verify-ca")]
+    VerifyCa,
+    #[serde(alias = "verify-full", alias = "verify_full")]
+    VerifyIdentity,
+}
+
+impl FromStr for SslMode {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().replace('-', "_").as_str() {
+            "disabled" | "disable" => Ok(SslMode::Disabled),
+            "preferred" | "prefer" => Ok(SslMode::Preferred),
+            "required" | "require" => Ok(SslMode::Required),
+            "verify_ca" => Ok(SslMode::VerifyCa),
+            "verify_identity" | "verify_full" => Ok(SslMode::VerifyIdentity),
+            _ => Err(()),
+        }
+    }
+}
+
+impl SslMode {
+    pub fn postgres_name(&self) -> &'static str {
+        match self {
+            SslMode::Disabled => "disable",
+            SslMode::Preferred => "prefer",
+            SslMode::Required => "require",
+            SslMode::VerifyCa => "verify-ca",
+            SslMode::VerifyIdentity => "verify-full",
+        }
+    }
+
+    pub fn mysql_name(&self) -> &'static str {
+        match self {
+            SslMode::Disabled => "DISABLED",
+            SslMode::Preferred => "PREFERRED",
+            SslMode::Required => "REQUIRED",
+            SslMode::VerifyCa => "VERIFY_CA",
+            SslMode::VerifyIdentity => "VERIFY_IDENTITY",
+        }
+    }
+
+    /// The server certificate must chain to a trusted CA
+    pub fn verifies_ca(&self) -> bool {
+        matches!(self, SslMode::VerifyCa | SslMode::VerifyIdentity)
+    }
+}
+
+/// How to reach a database beyond its url. Unset fields keep the driver's default.
+/// In the config file these sit next to `url` (`[database]`, `[datasources.<name>]`);
+/// from the environment they are `<PREFIX>_SSL_MODE`, `<PREFIX>_SSL_CA` ... with the
+/// driver prefix (`MYSQL`, `MARIADB`, `POSTGRES`, `SQLITE`) or `DATABASE`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectionOptions {
+    pub ssl_mode: Option<SslMode>,
+    /// CA certificate (PEM) the server certificate is checked against
+    pub ssl_ca: Option<String>,
+    /// Client certificate (PEM) for servers that require one
+    pub ssl_cert: Option<String>,
+    /// Private key (PEM) of `ssl_cert`
+    pub ssl_key: Option<String>,
+    /// Unix socket path; used instead of host and port
+    pub socket: Option<String>,
+    /// Seconds a query waits for a pooled connection, opening a new one included;
+    /// the driver has no separate limit for the tcp / tls connect
+    pub acquire_timeout_secs: Option<u64>,
+    pub charset: Option<String>,
+    /// Most connections kept open to this database
+    pub pool_size: Option<u32>,
+    /// Seconds between background pings of the pool, 0 turns them off
+    pub health_check_secs: Option<u64>,
+}
+
+impl ConnectionOptions {
+    pub fn from_env(env: &dyn Fn(&str) -> Option<String>, prefix: &str) -> Result<Self, ConfigError> {
+        let mut options = Self::default();
+        options.overlay_env(env, prefix)?;
+        Ok(options)
+    }
+
+    pub fn overlay_env(&mut self, env: &dyn Fn(&str) -> Option<String>, prefix: &str) -> Result<(), ConfigError> {
+        let mut error = ConfigError::default();
+        let key = |name: &str| format!("{}_{}", prefix, name);
+        if let Some(mode) = error.optional_parse(env, &key("SSL_MODE"), "disabled, preferred, required, verify_ca or verify_identity") {
+            self.ssl_mode = Some(mode);
+        }
+        for (name, field) in [
+            ("SSL_CA", &mut self.ssl_ca),
+            ("SSL_CERT", &mut self.ssl_cert),
+            ("SSL_KEY", &mut self.ssl_key),
+            ("SOCKET", &mut self.socket),
+            ("CHARSET", &mut self.charset),
+        ] {
+            if let Some(value) = env(&key(name)) {
+                *field = Some(value);
+            }
+        }
+        if let Some(secs) = error.optional_parse(env, &key("ACQUIRE_TIMEOUT_SECS"), "a number of seconds") {
+            self.acquire_timeout_secs = Some(secs);
+        }
+        if let Some(size) = error.optional_parse(env, &key("POOL_SIZE"), "a number of connections") {
+            self.pool_size = Some(size);
+        }
+        if let Some(secs) = error.optional_parse(env, &key("HEALTH_CHECK_SECS"), "a number of seconds") {
+            self.health_check_secs = Some(secs);
+        }
+        error.finish(())
+    }
+
+    /// Field by field, `self` wins over `fallback`
+    pub fn or(self, fallback: ConnectionOptions) -> Self {
+        Self {
+            ssl_mode: self.ssl_mode.or(fallback.ssl_mode),
+            ssl_ca: self.ssl_ca.or(fallback.ssl_ca),
+            ssl_cert: self.ssl_cert.or(fallback.ssl_cert),
+            ssl_key: self.ssl_key.or(fallback.ssl_key),
+            socket: self.socket.or(fallback.socket),
+            acquire_timeout_secs: self.acquire_timeout_secs.or(fallback.acquire_timeout_secs),
+            charset: self.charset.or(fallback.charset),
+            pool_size: self.pool_size.or(fallback.pool_size),
+            health_check_secs: self.health_check_secs.or(fallback.health_check_secs),
+        }
+    }
+
+    pub fn validate(&self, key: &str) -> Result<(), ConfigError> {
+        let mut error = ConfigError::default();
+        if self.ssl_cert.is_some() != self.ssl_key.is_some() {
+            error.push(&format!("{}.ssl_cert", key), "needs ssl_key as well (and the other way round)", "set both or neither");
+        }
+        if self.ssl_mode.is_some_and(|mode| mode.verifies_ca()) && self.ssl_ca.is_none() {
+            error.push(&format!("{}.ssl_ca", key), "is required to verify the server certificate", "point it at the CA certificate (PEM)");
+        }
+        if self.ssl_mode == Some(SslMode::Disabled) && (self.ssl_ca.is_some() || self.ssl_cert.is_some()) {
+            error.push(&format!("{}.ssl_mode", key), "is disabled but certificates are configured", "remove them or pick another ssl_mode");
+        }
+        if self.acquire_timeout_secs == Some(0) {
+            error.push(&format!("{}.acquire_timeout_secs", key), "is 0", "use a number of seconds above 0");
+        }
+        if self.pool_size == Some(0) {
+            error.push(&format!("{}.pool_size", key), "is 0", "use a number above 0");
+        }
+        error.finish(())
+    }
+
+    /// Names of the options that are set, for reporting options a datasource kind ignores
+    pub fn set_names(&self) -> Vec<&'static str> {
+        let set = [
+            ("ssl_mode", self.ssl_mode.is_some()),
+            ("ssl_ca", self.ssl_ca.is_some()),
+            ("ssl_cert", self.ssl_cert.is_some()),
+            ("ssl_key", self.ssl_key.is_some()),
+            ("socket", self.socket.is_some()),
+            ("acquire_timeout_secs", self.acquire_timeout_secs.is_some()),
+            ("charset", self.charset.is_some()),
+            ("pool_size", self.pool_size.is_some()),
+            ("health_check_secs", self.health_check_secs.is_some()),
+        ];
+        set.into_iter().filter(|(_, is_set)| *is_set).map(|(name, _)| name).collect()
+    }
+
+    /// libpq query parameters, which postgres drivers read from the url
+    pub fn postgres_params(&self) -> Vec<(&'static str, String)> {
+        let mut params = Vec::new();
+        if let Some(mode) = self.ssl_mode {
+            params.push(("sslmode", mode.postgres_name().to_string()));
+        }
+        let paths = [("sslrootcert", &self.ssl_ca), ("sslcert", &self.ssl_cert), ("sslkey", &self.ssl_key), ("host", &self.socket)];
+        for (name, value) in paths {
+            if let Some(value) = value {
+                params.push((name, value.clone()));
+            }
+        }
+        if let Some(charset) = &self.charset {
+            params.push(("client_encoding", charset.clone()));
+        }
+        params
+    }
+}
+
+/// `url?name=value&...`, keeping parameters the url already has
+pub fn with_query_params(url: &str, params: &[(&str, String)]) -> String {
+    if params.is_empty() {
+        return url.to_string();
+    }
+    let query: Vec<String> = params
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, encode_component(value)))
+        .collect();
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{}{}{}", url, separator, query.join("&"))
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{with_query_params, ConnectionOptions, SslMode};
+
+    #[test]
+    fn test_options_from_env() {
+        let env = |key: &str| match key {
+            "POSTGRES_SSL_MODE" => Some("verify-full".to_string()),
+            "POSTGRES_SSL_CA" => Some("/certs/ca.pem".to_string()),
+            "POSTGRES_POOL_SIZE" => Some("8".to_string()),
+            _ => None,
+        };
+        let options = ConnectionOptions::from_env(&env, "POSTGRES").unwrap();
+        assert_eq!(options.ssl_mode, Some(SslMode::VerifyIdentity));
+        assert_eq!(options.pool_size, Some(8));
+        assert!(options.validate("postgres").is_ok());
+
+        let url = with_query_params("postgres://app@db/hr", &options.postgres_params());
+        assert_eq!(url, "postgres://app@db/hr?sslmode=verify-full&sslrootcert=%2Fcerts%2Fca.pem");
+
+        let broken = ConnectionOptions { ssl_mode: Some(SslMode::VerifyCa), ssl_cert: Some("c.pem".into()), ..Default::default() };
+        assert_eq!(broken.validate("database").unwrap_err().problems.len(), 2);
+    }
+}
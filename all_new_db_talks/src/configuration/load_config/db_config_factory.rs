@@ -1,3 +1,5 @@
+use crate::configuration::config_error::ConfigError;
+
 use super::{maria::MariaDbConfig, mysql::MysqlConfig, postgrest::PostgresConfig, sqlite::SqliteConfig, trait_get_uri::DbLoadConfigTrait};
 
 pub enum DbConfig {
@@ -10,12 +12,14 @@ pub enum DbConfig {
 pub struct DatabaseFactory{}
 
 impl DatabaseFactory {
-    pub fn get_database_config(db_type: DbConfig) -> Box<dyn DbLoadConfigTrait>  {
-        match db_type {
-            DbConfig::MYSQL => Box::new(MysqlConfig::inject_env()),
-            DbConfig::MARIADB => Box::new(MariaDbConfig::inject_env()),
-            DbConfig::POSTGRES => Box::new(PostgresConfig::inject_env()),
-            DbConfig::SQLITE => Box::new(SqliteConfig::inject_env()),
-        }
+    /// Fails with every missing or invalid key of the chosen database; `env` is the one
+    /// `AppConfig::load` read, with `.env` applied
+    pub fn get_database_config(db_type: DbConfig, env: &dyn Fn(&str) -> Option<String>) -> Result<Box<dyn DbLoadConfigTrait>, ConfigError>  {
+        Ok(match db_type {
+            DbConfig::MYSQL => Box::new(MysqlConfig::from_env(env)?),
+            DbConfig::MARIADB => Box::new(MariaDbConfig::from_env(env)?),
+            DbConfig::POSTGRES => Box::new(PostgresConfig::from_env(env)?),
+            DbConfig::SQLITE => Box::new(SqliteConfig::from_env(env)?),
+        })
     }
 }
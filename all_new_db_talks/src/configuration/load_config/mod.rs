@@ -3,4 +3,5 @@ pub mod mysql;
 pub mod postgrest;
 pub mod sqlite;
 pub mod db_config_factory;
-pub mod trait_get_uri;
\ No newline at end of file
+pub mod trait_get_uri;
+pub mod connection_options;
\ No newline at end of file
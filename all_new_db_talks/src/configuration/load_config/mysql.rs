@@ -1,50 +1,65 @@
-use dotenv::dotenv;
-use super::trait_get_uri::DbLoadConfigTrait;
+use super::{connection_options::ConnectionOptions, trait_get_uri::DbLoadConfigTrait};
+use crate::configuration::{
+    config_error::ConfigError,
+    secret::{encode_component, Secret},
+};
 
+const HINT: &str = "add it to .env or export it";
+
+#[derive(Debug)]
 pub struct MysqlConfig {
     pub username: String,
-    pub password: String,
+    pub password: Secret,
     pub port: u16,
     pub db_name: String,
     pub ip_address: String,
+    pub options: ConnectionOptions,
 }
 
 impl MysqlConfig {
-    pub fn inject_env() -> Self {
-        dotenv().ok();
-        let retrive_username = std::env::var("MYSQL_USERNAME")
-            .expect("MYSQL_USERNAME in env not found!, please review");
-        let retrive_password = std::env::var("MYSQL_PASSWORD")
-            .expect("MYSQL_PASSWORD in env not found!, please review");
-        let retrive_port: u16 = std::env::var("MYSQL_PORT")
-            .expect("MYSQL_PORT in env not found!, please review")
-            .parse()
-            .expect("MYSQL_PORT must be a valid u16 number");
-        let retrive_db_name = std::env::var("MYSQL_DB_NAME")
-            .expect("MYSQL_DB_NAME in env not found!, please review");
-        let retrive_id_address = std::env::var("MYSQL_IP_ADDRESS")
-            .expect("MYSQL_IP_ADDRESS in env not found!, please review");
+    /// Reads every key before failing, so all missing or invalid ones are reported together
+    pub fn from_env(env: &dyn Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let mut error = ConfigError::default();
+        let username = error.required(env, "MYSQL_USERNAME", HINT);
+        let password = error.required_secret(
+            env,
+            "MYSQL_PASSWORD",
+            "set it or MYSQL_PASSWORD_FILE, empty is allowed for a user without password",
+        );
+        let port: Option<u16> = error.required_parse(env, "MYSQL_PORT", "a port number (e.g. 3306)");
+        let db_name = error.required(env, "MYSQL_DB_NAME", HINT);
+        let ip_address = error.required(env, "MYSQL_IP_ADDRESS", HINT);
+        let options = error.absorb(ConnectionOptions::from_env(env, "MYSQL"));
 
-        Self { 
-            username: retrive_username, 
-            password: retrive_password, 
-            port: retrive_port, 
-            db_name: retrive_db_name, 
-            ip_address: retrive_id_address }
+        match (username, password, port, db_name, ip_address, options) {
+            (Some(username), Some(password), Some(port), Some(db_name), Some(ip_address), Some(options)) => Ok(Self {
+                username,
+                password,
+                port,
+                db_name,
+                ip_address,
+                options,
+            }),
+            _ => Err(error),
+        }
     }
-
-    
 }
 
+
 impl DbLoadConfigTrait for MysqlConfig {
-    fn get_url(&self) -> String {
-        format!(
+    /// User, password and database name are url-encoded
+    fn get_url(&self) -> Secret {
+        Secret::new(format!(
             "mysql://{}:{}@{}:{}/{}",
-            self.username,
-            self.password,
+            encode_component(&self.username),
+            encode_component(self.password.expose()),
             self.ip_address,
             self.port,
-            self.db_name
-        )
+            encode_component(&self.db_name)
+        ))
+    }
+
+    fn options(&self) -> ConnectionOptions {
+        self.options.clone()
     }
 }
\ No newline at end of file
@@ -1,54 +1,66 @@
-use std::env;
-use dotenv::dotenv;
+use super::{connection_options::{with_query_params, ConnectionOptions}, trait_get_uri::DbLoadConfigTrait};
+use crate::configuration::{
+    config_error::ConfigError,
+    secret::{encode_component, Secret},
+};
 
-use super::trait_get_uri::DbLoadConfigTrait;
+const HINT: &str = "add it to .env or export it";
 
+#[derive(Debug)]
 pub struct PostgresConfig {
     pub username: String,
-    pub password: String,
+    pub password: Secret,
     pub host: String,
     pub port: u16,
     pub db_name: String,
+    pub options: ConnectionOptions,
 }
 
 impl PostgresConfig {
-    pub fn inject_env() -> Self {
-        dotenv().ok();
+    /// Reads every key before failing, so all missing or invalid ones are reported together
+    pub fn from_env(env: &dyn Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let mut error = ConfigError::default();
+        let username = error.required(env, "POSTGRES_USERNAME", HINT);
+        let password = error.required_secret(
+            env,
+            "POSTGRES_PASSWORD",
+            "set it or POSTGRES_PASSWORD_FILE, empty is allowed for a user without password",
+        );
+        let host = error.required(env, "POSTGRES_HOST", HINT);
+        let port: Option<u16> = error.required_parse(env, "POSTGRES_PORT", "a port number (e.g. 5432)");
+        let db_name = error.required(env, "POSTGRES_DB_NAME", HINT);
+        let options = error.absorb(ConnectionOptions::from_env(env, "POSTGRES"));
 
-        let username = env::var("POSTGRES_USERNAME")
-            .expect("POSTGRES_USERNAME in env not found!, please review");
-
-        let password = env::var("POSTGRES_PASSWORD")
-            .expect("POSTGRES_PASSWORD in env not found!, please review");
-
-        let host = env::var("POSTGRES_HOST")
-            .expect("POSTGRES_HOST in env not found!, please review");
-
-        let port: u16 = env::var("POSTGRES_PORT")
-            .expect("POSTGRES_PORT in env not found!, please review")
-            .parse()
-            .expect("POSTGRES_PORT must be a valid u16 number");
-
-        let db_name = env::var("POSTGRES_DB_NAME")
-            .expect("POSTGRES_DB_NAME in env not found!, please review");
-
-        Self {
-            username,
-            password,
-            host,
-            port,
-            db_name,
+        match (username, password, host, port, db_name, options) {
+            (Some(username), Some(password), Some(host), Some(port), Some(db_name), Some(options)) => Ok(Self {
+                username,
+                password,
+                host,
+                port,
+                db_name,
+                options,
+            }),
+            _ => Err(error),
         }
     }
-
-   
 }
 
+
 impl DbLoadConfigTrait for PostgresConfig {
-    fn get_url(&self) -> String {
-        format!(
+    /// User, password and database name are url-encoded; options become libpq parameters
+    fn get_url(&self) -> Secret {
+        let url = format!(
             "postgres://{}:{}@{}:{}/{}",
-            self.username, self.password, self.host, self.port, self.db_name
-        )
+            encode_component(&self.username),
+            encode_component(self.password.expose()),
+            self.host,
+            self.port,
+            encode_component(&self.db_name)
+        );
+        Secret::new(with_query_params(&url, &self.options.postgres_params()))
+    }
+
+    fn options(&self) -> ConnectionOptions {
+        self.options.clone()
     }
 }
\ No newline at end of file
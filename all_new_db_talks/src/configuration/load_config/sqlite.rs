@@ -1,30 +1,41 @@
-use std::env;
-use dotenv::dotenv;
+use super::{connection_options::ConnectionOptions, trait_get_uri::DbLoadConfigTrait};
+use crate::configuration::{config_error::ConfigError, secret::Secret};
 
-use super::trait_get_uri::DbLoadConfigTrait;
+const HINT: &str = "add it to .env or export it";
 
+#[derive(Debug)]
 pub struct SqliteConfig {
     pub db_dir: String,
     pub db_name: String,
+    pub options: ConnectionOptions,
 }
 
 impl SqliteConfig {
-    pub fn inject_env() -> Self {
-        dotenv().ok();
-        let db_dir = env::var("SQLITE_DB_DIR")
-            .expect("SQLITE_DB_DIR in env not found!, please review");
+    /// Reads every key before failing, so all missing or invalid ones are reported together
+    pub fn from_env(env: &dyn Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let mut error = ConfigError::default();
+        let db_dir = error.required(env, "SQLITE_DB_DIR", HINT);
+        let db_name = error.required(env, "SQLITE_DB_NAME", HINT);
+        let options = error.absorb(ConnectionOptions::from_env(env, "SQLITE"));
 
-        let db_name = env::var("SQLITE_DB_NAME")
-            .expect("SQLITE_DB_NAME in env not found!, please review");
-
-        Self { db_dir, db_name }
+        match (db_dir, db_name, options) {
+            (Some(db_dir), Some(db_name), Some(options)) => Ok(Self {
+                db_dir,
+                db_name,
+                options,
+            }),
+            _ => Err(error),
+        }
     }
-
-    
 }
 
+
 impl DbLoadConfigTrait for SqliteConfig {
-    fn get_url(&self) -> String {
-        format!("sqlite://{}/{}", self.db_dir.trim_end_matches('/'), self.db_name)
+    fn get_url(&self) -> Secret {
+        Secret::new(format!("sqlite://{}/{}", self.db_dir.trim_end_matches('/'), self.db_name))
     }
-}
+
+    fn options(&self) -> ConnectionOptions {
+        self.options.clone()
+    }
+}
\ No newline at end of file
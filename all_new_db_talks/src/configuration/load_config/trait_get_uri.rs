@@ -1,3 +1,9 @@
+use super::connection_options::ConnectionOptions;
+use crate::configuration::secret::Secret;
+
 pub trait DbLoadConfigTrait {
-    fn get_url(&self) -> String;
+    /// The connection url; it carries the password, so it stays a `Secret`
+    fn get_url(&self) -> Secret;
+    /// Ssl, socket, timeout and pool settings read next to the url
+    fn options(&self) -> ConnectionOptions;
 }
\ No newline at end of file
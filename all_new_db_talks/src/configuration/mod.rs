@@ -1,4 +1,13 @@
 pub mod model_config;
 pub mod llm_config;
 pub mod db_config;
-pub mod load_config;
\ No newline at end of file
+pub mod load_config;
+pub mod session_config;
+pub mod repl_config;
+pub mod auth_config;
+pub mod limits_config;
+pub mod app_config;
+pub mod config_error;
+pub mod datasource_config;
+pub mod secret;
+pub mod csv_config;
\ No newline at end of file
@@ -1,20 +1,224 @@
-use std::env;
+use std::collections::HashMap;
 
+use crate::configuration::config_error::ConfigError;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ModelSelect {
+    #[serde(alias = "sql")]
     SqlOperate,
+    #[serde(alias = "npl")]
     NplOperate,
+    #[serde(rename = "tiny_llama", alias = "tiny")]
     TinyLlma
 }
 
 impl ModelSelect {
-    pub fn as_str(&self) -> String {
-        let sql = env::var("SQL_OPERATE").unwrap_or_else(|_| "sqlcoder:7b".to_string());
-        let npl = env::var("NPL_OPERATE").unwrap_or_else(|_| "llama3.1:8b".to_string());
-        let tiny_llama = env::var("TINY_LLAMA").unwrap_or_else(|_| "timyllama:latest".to_string());
+    pub const ALL: [ModelSelect; 3] = [ModelSelect::SqlOperate, ModelSelect::NplOperate, ModelSelect::TinyLlma];
+
+    /// Primary model configured for this role
+    pub fn model<'a>(&self, config: &'a ModelRouterConfig) -> &'a str {
+        &config.role(*self).primary
+    }
+
+    pub fn env_prefix(&self) -> &'static str {
+        match self {
+            ModelSelect::SqlOperate => "SQL_OPERATE",
+            ModelSelect::NplOperate => "NPL_OPERATE",
+            ModelSelect::TinyLlma => "TINY_LLAMA"
+        }
+    }
+
+    pub fn default_model(&self) -> &'static str {
+        match self {
+            ModelSelect::SqlOperate => "sqlcoder:7b",
+            ModelSelect::NplOperate => "llama3.1:8b",
+            ModelSelect::TinyLlma => "timyllama:latest"
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "sql" | "sql_operate" | "sqloperate" => Some(ModelSelect::SqlOperate),
+            "npl" | "npl_operate" | "nploperate" => Some(ModelSelect::NplOperate),
+            "tiny" | "tiny_llama" | "tinyllma" => Some(ModelSelect::TinyLlma),
+            _ => None
+        }
+    }
+}
+
+/// The kinds of work the chain hands to a model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelTask {
+    #[serde(alias = "intent")]
+    IntentClassification,
+    SqlGeneration,
+    SqlRepair,
+    Summarization,
+    Clarification
+}
+
+impl ModelTask {
+    pub const ALL: [ModelTask; 5] = [
+        ModelTask::IntentClassification,
+        ModelTask::SqlGeneration,
+        ModelTask::SqlRepair,
+        ModelTask::Summarization,
+        ModelTask::Clarification
+    ];
+
+    pub fn env_key(&self) -> &'static str {
+        match self {
+            ModelTask::IntentClassification => "ROUTE_INTENT",
+            ModelTask::SqlGeneration => "ROUTE_SQL_GENERATION",
+            ModelTask::SqlRepair => "ROUTE_SQL_REPAIR",
+            ModelTask::Summarization => "ROUTE_SUMMARIZATION",
+            ModelTask::Clarification => "ROUTE_CLARIFICATION"
+        }
+    }
+
+    pub fn default_role(&self) -> ModelSelect {
         match self {
-            ModelSelect::SqlOperate => sql,
-            ModelSelect::NplOperate => npl,
-            ModelSelect::TinyLlma => tiny_llama
+            ModelTask::IntentClassification => ModelSelect::TinyLlma,
+            ModelTask::SqlGeneration => ModelSelect::SqlOperate,
+            ModelTask::SqlRepair => ModelSelect::SqlOperate,
+            ModelTask::Summarization => ModelSelect::NplOperate,
+            ModelTask::Clarification => ModelSelect::NplOperate
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelRoleConfig {
+    pub primary: String,
+    #[serde(default)]
+    pub fallbacks: Vec<String>,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_timeout_secs() -> u64 {
+    ModelRouterConfig::DEFAULT_TIMEOUT_SECS
+}
+
+impl ModelRoleConfig {
+    /// Primary first, then fallbacks in configured order, without duplicates
+    pub fn candidates(&self) -> Vec<String> {
+        let mut models = vec![self.primary.clone()];
+        for fallback in &self.fallbacks {
+            if !models.contains(fallback) {
+                models.push(fallback.clone());
+            }
+        }
+        models
+    }
+}
+
+/// `[models]` in the config file:
+/// `[models.roles.sql_operate]` with `primary`, `fallbacks`, `timeout_secs`,
+/// and `[models.routes]` mapping a task to a role (`sql_generation = "sql_operate"`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelRouterConfig {
+    pub roles: HashMap<ModelSelect, ModelRoleConfig>,
+    pub routes: HashMap<ModelTask, ModelSelect>,
+}
+
+impl Default for ModelRouterConfig {
+    fn default() -> Self {
+        let mut config = Self { roles: HashMap::new(), routes: HashMap::new() };
+        config.fill_defaults();
+        config
+    }
+}
+
+impl ModelRouterConfig {
+    pub const DEFAULT_TIMEOUT_SECS: u64 = 60;
+
+    /// Give roles and routes left out of the config file their defaults
+    pub fn fill_defaults(&mut self) {
+        for role in ModelSelect::ALL {
+            self.roles.entry(role).or_insert_with(|| ModelRoleConfig {
+                primary: role.default_model().to_string(),
+                fallbacks: Vec::new(),
+                timeout_secs: Self::DEFAULT_TIMEOUT_SECS,
+            });
+        }
+        for task in ModelTask::ALL {
+            self.routes.entry(task).or_insert(task.default_role());
+        }
+    }
+
+    /// Overlay env: `<ROLE>` model name, `<ROLE>_FALLBACK` comma separated models,
+    /// `MODEL_TIMEOUT_SECS` / `<ROLE>_TIMEOUT_SECS`, and `ROUTE_<TASK>` = sql | npl | tiny
+    pub fn overlay_env(&mut self, env: &dyn Fn(&str) -> Option<String>) -> Result<(), ConfigError> {
+        self.fill_defaults();
+        let mut error = ConfigError::default();
+        let seconds = "a number of seconds";
+
+        let default_timeout = error.optional_parse(env, "MODEL_TIMEOUT_SECS", seconds);
+        for role in ModelSelect::ALL {
+            let prefix = role.env_prefix();
+            let config = self.roles.get_mut(&role).expect("filled above");
+            if let Some(primary) = env(prefix) {
+                config.primary = primary;
+            }
+            if let Some(fallbacks) = env(&format!("{}_FALLBACK", prefix)) {
+                config.fallbacks = Self::split_list(&fallbacks);
+            }
+            let timeout_key = format!("{}_TIMEOUT_SECS", prefix);
+            if let Some(timeout_secs) = error.optional_parse(env, &timeout_key, seconds).or(default_timeout) {
+                config.timeout_secs = timeout_secs;
+            }
+        }
+
+        for task in ModelTask::ALL {
+            if let Some(data) = env(task.env_key()) {
+                match ModelSelect::parse(&data) {
+                    Some(role) => {
+                        self.routes.insert(task, role);
+                    }
+                    None => error.push(task.env_key(), format!("'{}' is not a model role", data), "use sql, npl or tiny"),
+                }
+            }
+        }
+        error.finish(())
+    }
+
+    /// Every role needs a model name
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut error = ConfigError::default();
+        for role in ModelSelect::ALL {
+            if self.roles.get(&role).is_none_or(|config| config.primary.trim().is_empty()) {
+                error.push(
+                    role.env_prefix(),
+                    "has no model",
+                    format!("set [models.roles.*] primary or {}, e.g. {}", role.env_prefix(), role.default_model()),
+                );
+            }
         }
+        error.finish(())
+    }
+
+    pub fn role(&self, role: ModelSelect) -> &ModelRoleConfig {
+        self.roles.get(&role).expect("every ModelSelect role is configured")
     }
-}
\ No newline at end of file
+
+    pub fn role_for(&self, task: ModelTask) -> ModelSelect {
+        self.routes.get(&task).copied().unwrap_or(task.default_role())
+    }
+
+    /// Models to try for a task: the routed role's primary, then its fallbacks
+    pub fn candidates_for(&self, task: ModelTask) -> Vec<String> {
+        self.role(self.role_for(task)).candidates()
+    }
+
+    fn split_list(data: &str) -> Vec<String> {
+        data.split(',')
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect()
+    }
+}
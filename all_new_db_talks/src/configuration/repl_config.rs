@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+/// `[repl]` in the config file, overlaid by `REPL_HISTORY_PATH` / `SESSION_ID`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ReplConfig {
+    pub history_path: String,
+    /// Resume this stored conversation on start
+    pub session_id: Option<String>
+}
+
+impl Default for ReplConfig {
+    fn default() -> Self {
+        Self { history_path: ".talk_with_db_history".to_string(), session_id: None }
+    }
+}
+
+impl ReplConfig {
+    pub fn overlay_env(&mut self, env: &dyn Fn(&str) -> Option<String>) {
+        if let Some(history_path) = env("REPL_HISTORY_PATH") {
+            self.history_path = history_path;
+        }
+        if let Some(session_id) = env("SESSION_ID").filter(|id| !id.trim().is_empty()) {
+            self.session_id = Some(session_id);
+        }
+    }
+}
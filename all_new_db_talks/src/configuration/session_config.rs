@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+/// `[session]` in the config file, overlaid by `SESSION_DB_PATH`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionStoreConfig {
+    pub db_path: String
+}
+
+impl Default for SessionStoreConfig {
+    fn default() -> Self {
+        Self { db_path: "sessions.db".to_string() }
+    }
+}
+
+impl SessionStoreConfig {
+    pub fn overlay_env(&mut self, env: &dyn Fn(&str) -> Option<String>) {
+        if let Some(db_path) = env("SESSION_DB_PATH") {
+            self.db_path = db_path;
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use anyhow::{anyhow, Result};
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{Bool, Text};
+use diesel::{Connection, RunQueryDsl, SqliteConnection};
+use diesel_migrations::MigrationHarness;
+
+use crate::agent::access_policy::ApiKeyPolicy;
+use crate::datasource::session_store::MIGRATIONS;
+
+/// Api keys kept next to the sessions in the local SQLite file.
+/// Lists are stored comma separated.
+pub struct ApiKeyStore {
+    pub db_con: SqliteConnection,
+}
+
+#[derive(Debug, QueryableByName)]
+struct ApiKeyRow {
+    #[diesel(sql_type = Text)]
+    name: String,
+    #[diesel(sql_type = Text)]
+    key_hash: String,
+    #[diesel(sql_type = Text)]
+    datasources: String,
+    #[diesel(sql_type = Text)]
+    allowed_tables: String,
+    #[diesel(sql_type = Bool)]
+    allow_write: bool,
+}
+
+impl From<ApiKeyRow> for ApiKeyPolicy {
+    fn from(row: ApiKeyRow) -> Self {
+        let split = |list: &str| list.split(',').map(|item| item.trim().to_string()).filter(|item| !item.is_empty()).collect();
+        Self {
+            name: row.name,
+            key_hash: row.key_hash,
+            datasources: split(&row.datasources),
+            tables: split(&row.allowed_tables),
+            allow_write: row.allow_write,
+        }
+    }
+}
+
+impl ApiKeyStore {
+    /// Usually the same file as the session store (`[session] db_path`)
+    pub fn open(db_path: &str) -> Result<Self> {
+        let mut connection = SqliteConnection::establish(db_path)?;
+        connection
+            .run_pending_migrations(MIGRATIONS)
+            .map_err(|err| anyhow!("failed to migrate api key store '{}': {}", db_path, err))?;
+        Ok(Self { db_con: connection })
+    }
+
+    pub fn add(&mut self, policy: &ApiKeyPolicy) -> Result<()> {
+        sql_query(
+            "INSERT INTO api_keys (name, key_hash, datasources, allowed_tables, allow_write) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind::<Text, _>(&policy.name)
+        .bind::<Text, _>(&policy.key_hash)
+        .bind::<Text, _>(policy.datasources.join(","))
+        .bind::<Text, _>(policy.tables.join(","))
+        .bind::<Bool, _>(policy.allow_write)
+        .execute(&mut self.db_con)
+        .map_err(|err| anyhow!("failed to add api key '{}': {}", policy.name, err))?;
+        Ok(())
+    }
+
+    pub fn find_by_hash(&mut self, key_hash: &str) -> Result<Option<ApiKeyPolicy>> {
+        let rows: Vec<ApiKeyRow> = sql_query(
+            "SELECT name, key_hash, datasources, allowed_tables, allow_write FROM api_keys WHERE key_hash = ?",
+        )
+        .bind::<Text, _>(key_hash)
+        .load(&mut self.db_con)?;
+        Ok(rows.into_iter().next().map(ApiKeyPolicy::from))
+    }
+
+    pub fn list(&mut self) -> Result<Vec<ApiKeyPolicy>> {
+        let rows: Vec<ApiKeyRow> = sql_query(
+            "SELECT name, key_hash, datasources, allowed_tables, allow_write FROM api_keys ORDER BY name",
+        )
+        .load(&mut self.db_con)?;
+        Ok(rows.into_iter().map(ApiKeyPolicy::from).collect())
+    }
+
+    /// Returns false when no key had that name
+    pub fn revoke(&mut self, name: &str) -> Result<bool> {
+        let deleted = sql_query("DELETE FROM api_keys WHERE name = ?")
+            .bind::<Text, _>(name)
+            .execute(&mut self.db_con)?;
+        Ok(deleted > 0)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::ApiKeyStore;
+    use crate::agent::access_policy::{hash_api_key, ApiKeyPolicy};
+
+    #[test]
+    fn test_add_find_revoke_key() {
+        let mut store = ApiKeyStore::open(":memory:").unwrap();
+        let mut policy = ApiKeyPolicy::new("bi-dashboard", "secret");
+        policy.tables = vec!["Invoice".to_string(), "Customer".to_string()];
+        store.add(&policy).unwrap();
+
+        let found = store.find_by_hash(&hash_api_key("secret")).unwrap().unwrap();
+        assert_eq!(found.tables, ["Invoice", "Customer"]);
+        assert!(!found.allow_write);
+        assert!(store.revoke("bi-dashboard").unwrap());
+        assert!(store.find_by_hash(&hash_api_key("secret")).unwrap().is_none());
+    }
+}
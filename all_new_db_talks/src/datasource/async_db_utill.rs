@@ -1,33 +1,124 @@
 use std::collections::HashMap;
 
-use anyhow::Error;
-use mysql::{prelude::Queryable, Row};
+use anyhow::{anyhow, Error};
+use sqlx::{
+    mysql::{MySqlColumn, MySqlPool, MySqlRow},
+    Column, Executor, Row, TypeInfo, ValueRef,
+};
+use tokio_stream::StreamExt;
 
-use crate::configuration::db_config::DatabaseConfig;
+use crate::{configuration::{app_config::AppConfig, load_config::connection_options::ConnectionOptions}, datasource::connection::sqlx_pool, datasource::db_utill::{ColumnName, DatabaseSchema, TableSchema}, datasource::result_set::{ColumnKind, ResultColumn, ResultSet}};
 
 
 
+/// Cheap to clone; clones share the same connections.
+/// User sql is prepared before it runs, so the server refuses stacked statements
+/// whatever got past validation; only the constant ping uses the text protocol.
+#[derive(Clone)]
 pub struct AsyncDb {
-    pool: mysql::Pool,
+    pool: MySqlPool,
 }
 
 impl AsyncDb {
-    pub fn new() -> Result<Self, Error> {
-        let db_url = DatabaseConfig::inject_from_env().db_url;
-        let pool = mysql::Pool::new(mysql::Opts::from_url(&db_url)?)?;
-        Ok(Self { pool })
+    /// Pool for `[database]` of an already loaded config
+    pub async fn new(config: &AppConfig) -> Result<Self, Error> {
+        Self::connect_with(config.database.db_url.expose(), &config.database.options).await
     }
 
-    pub async fn query(&self, query: &str) -> Result<Vec<HashMap<String, String>>, Box<dyn std::error::Error>> {
-        let mut conn = self.pool.get_conn()?;
-        let result: Vec<Row> = conn.query(query)?;
+    pub async fn connect(db_url: &str) -> Result<Self, Error> {
+        Self::connect_with(db_url, &ConnectionOptions::default()).await
+    }
+
+    pub async fn connect_with(db_url: &str, options: &ConnectionOptions) -> Result<Self, Error> {
+        Ok(Self { pool: sqlx_pool(db_url, options).await? })
+    }
+
+    /// Round trip on one pooled connection
+    pub async fn ping(&self) -> Result<(), Error> {
+        sqlx::raw_sql("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+
+    /// Whether `err` means the server could not be reached, as opposed to a failing statement
+    pub fn is_connection_error(err: &Error) -> bool {
+        matches!(
+            err.downcast_ref::<sqlx::Error>(),
+            Some(
+                sqlx::Error::Io(_)
+                    | sqlx::Error::Tls(_)
+                    | sqlx::Error::Protocol(_)
+                    | sqlx::Error::PoolTimedOut
+                    | sqlx::Error::PoolClosed
+                    | sqlx::Error::WorkerCrashed
+            )
+        )
+    }
+
+    /// Tables and columns of the connected database, in column order
+    pub async fn database_schema(&self) -> Result<DatabaseSchema, Error> {
+        let columns: Vec<(String, String)> = sqlx::query_as(
+            "SELECT table_name, column_name FROM information_schema.columns \
+             WHERE table_schema = DATABASE() ORDER BY table_name, ordinal_position",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut schema = DatabaseSchema::default();
+        for (table_name, column_name) in columns {
+            if schema.schemas.last().is_none_or(|table| table.table_name != table_name) {
+                schema.schemas.push(TableSchema { table_name, ..Default::default() });
+            }
+            if let Some(table) = schema.schemas.last_mut() {
+                table.field_columns.push(ColumnName::new(&column_name));
+            }
+        }
+        Ok(schema)
+    }
+
+    pub async fn table_names(&self) -> Result<Vec<String>, Error> {
+        let tables = sqlx::query_scalar(
+            "SELECT table_name FROM information_schema.tables WHERE table_schema = DATABASE() ORDER BY table_name",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(tables)
+    }
+
+    /// Columns of one table, or `None` when the table does not exist
+    pub async fn describe_table(&self, table_name: &str) -> Result<Option<TableSchema>, Error> {
+        let columns: Vec<(String, String)> = sqlx::query_as(
+            "SELECT table_name, column_name FROM information_schema.columns \
+             WHERE table_schema = DATABASE() AND LOWER(table_name) = LOWER(?) ORDER BY ordinal_position",
+        )
+        .bind(table_name)
+        .fetch_all(&self.pool)
+        .await?;
+        let Some((table_name, _)) = columns.first().cloned() else {
+            return Ok(None);
+        };
+        let field_columns = columns.iter().map(|(_, column)| ColumnName::new(column)).collect();
+        Ok(Some(TableSchema { table_name, field_columns }))
+    }
+
+    /// Reads at most `limit` rows; the rest are never decoded
+    async fn fetch_rows(&self, query: &str, limit: Option<usize>) -> Result<Vec<MySqlRow>, Error> {
+        let mut stream = sqlx::query(query).fetch(&self.pool).take(limit.unwrap_or(usize::MAX));
+        let mut rows = Vec::new();
+        while let Some(row) = stream.next().await {
+            rows.push(row?);
+        }
+        Ok(rows)
+    }
+
+    pub async fn query(&self, query: &str, limit: Option<usize>) -> Result<Vec<HashMap<String, String>>, Error> {
+        let result = self.fetch_rows(query, limit).await?;
 
         let mut rows = Vec::new();
         for row in result {
             let mut map = HashMap::new();
-            for (i, col) in row.columns_ref().iter().enumerate() {
-                let key = col.name_str().to_string();
-                let value = row.as_ref(i).map(Self::value_to_string).unwrap_or_default();
+            for (i, col) in row.columns().iter().enumerate() {
+                let key = col.name().to_string();
+                let value = Self::cell_text(&row, i, col)?.unwrap_or_else(|| "NULL".to_string());
                 map.insert(key, value);
             }
             rows.push(map);
@@ -36,10 +127,86 @@ impl AsyncDb {
         Ok(rows)
     }
 
+    /// Like `query`, but keeps column order and converts values to their column type
+    pub async fn query_typed(&self, query: &str, limit: Option<usize>) -> Result<ResultSet, Error> {
+        let result = self.fetch_rows(query, limit).await?;
+        let columns = match result.first() {
+            Some(row) => Self::result_columns(row.columns()),
+            // no row carries the columns, so ask the server to describe the statement
+            None => match self.pool.describe(query).await {
+                Ok(described) => Self::result_columns(described.columns()),
+                Err(_) => Vec::new(),
+            },
+        };
+
+        let mut rows = Vec::new();
+        for row in result {
+            let mut values = Vec::with_capacity(columns.len());
+            for (i, column) in row.columns().iter().enumerate() {
+                let text = Self::cell_text(&row, i, column).map_err(|err| anyhow!("failed to read row: {}", err))?;
+                values.push(match text {
+                    None => serde_json::Value::Null,
+                    Some(text) => ResultSet::typed_value(Self::column_kind(column.type_info().name()), &text),
+                });
+            }
+            rows.push(values);
+        }
+
+        Ok(ResultSet { columns, rows })
+    }
+
+    fn result_columns(columns: &[MySqlColumn]) -> Vec<ResultColumn> {
+        columns
+            .iter()
+            .map(|col| ResultColumn {
+                name: col.name().to_string(),
+                data_type: Self::column_kind(col.type_info().name()),
+            })
+            .collect()
+    }
+
+    /// The value as text, `None` for NULL. Prepared statements answer in the binary
+    /// protocol, so numbers and dates are decoded by column type; decimals and
+    /// strings arrive as text, BIT as raw bytes that are turned into their number.
+    fn cell_text(row: &MySqlRow, i: usize, column: &MySqlColumn) -> Result<Option<String>, Error> {
+        if row.try_get_raw(i)?.is_null() {
+            return Ok(None);
+        }
+        let text = match column.type_info().name() {
+            "TINYINT" | "SMALLINT" | "MEDIUMINT" | "INT" | "BIGINT" | "YEAR" | "BOOLEAN" => row.try_get_unchecked::<i64, _>(i)?.to_string(),
+            name if name.ends_with(" UNSIGNED") => row.try_get_unchecked::<u64, _>(i)?.to_string(),
+            "FLOAT" => row.try_get_unchecked::<f32, _>(i)?.to_string(),
+            "DOUBLE" => row.try_get_unchecked::<f64, _>(i)?.to_string(),
+            name @ ("DATE" | "DATETIME" | "TIMESTAMP") => binary_datetime(&row.try_get_unchecked::<Vec<u8>, _>(i)?, name == "DATE"),
+            "TIME" => binary_time(&row.try_get_unchecked::<Vec<u8>, _>(i)?),
+            "BIT" => {
+                let bytes: Vec<u8> = row.try_get_unchecked(i)?;
+                bytes.iter().fold(0u64, |number, byte| (number << 8) | u64::from(*byte)).to_string()
+            }
+            _ => String::from_utf8_lossy(&row.try_get_unchecked::<Vec<u8>, _>(i)?).to_string(),
+        };
+        Ok(Some(text))
+    }
+
+    fn column_kind(type_name: &str) -> ColumnKind {
+        match type_name.trim_end_matches(" UNSIGNED") {
+            "TINYINT" | "SMALLINT" | "MEDIUMINT" | "INT" | "BIGINT" | "YEAR" => ColumnKind::Integer,
+            "FLOAT" | "DOUBLE" => ColumnKind::Float,
+            "DECIMAL" => ColumnKind::Decimal,
+            "BOOLEAN" | "BIT" => ColumnKind::Boolean,
+            "DATE" => ColumnKind::Date,
+            "DATETIME" | "TIMESTAMP" => ColumnKind::DateTime,
+            "TIME" => ColumnKind::Time,
+            "JSON" => ColumnKind::Json,
+            "TINYBLOB" | "BLOB" | "MEDIUMBLOB" | "LONGBLOB" | "BINARY" | "VARBINARY" | "GEOMETRY" => ColumnKind::Binary,
+            "NULL" => ColumnKind::Null,
+            _ => ColumnKind::Text,
+        }
+    }
+
     pub async fn query_as_string(&self, generated_query: String) -> Result<String, Error> {
-        let async_db = AsyncDb::new()?;
-        let result = async_db.query(&generated_query).await.unwrap();
-    
+        let result = self.query(&generated_query, None).await?;
+
         let mut output = String::new();
         for (i, row) in result.iter().enumerate() {
             output.push_str(&format!("Row {}:\n", i + 1));
@@ -47,30 +214,48 @@ impl AsyncDb {
                 output.push_str(&format!("  {}: {}\n", key, value));
             }
         }
-    
+
         Ok(output)
     }
+}
 
-    fn value_to_string(val: &mysql::Value) -> String {
-        match val {
-            mysql::Value::NULL => "NULL".to_string(),
-            mysql::Value::Bytes(bytes) => String::from_utf8_lossy(bytes).to_string(),
-            mysql::Value::Int(i) => i.to_string(),
-            mysql::Value::UInt(u) => u.to_string(),
-            mysql::Value::Float(f) => f.to_string(),
-            mysql::Value::Double(d) => d.to_string(),
-            mysql::Value::Date(y, m, d, h, min, s, micros) => {
-                format!(
-                    "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:06}",
-                    y, m, d, h, min, s, micros
-                )
-            }
-            mysql::Value::Time(is_neg, d, h, m, s, micros) => {
-                let sign = if *is_neg { "-" } else { "" };
-                // Dereferencing `d` and `h` here:
-                let total_hours = (*d as u32) * 24 + (*h as u32);
-                format!("{sign}{:03}:{:02}:{:02}.{:06}", total_hours, m, s, micros)
-            }
-        }
+/// Binary DATE / DATETIME: a length byte, then year (2 bytes LE), month, day,
+/// hour, minute, second and microseconds (4 bytes LE); trailing zero parts are left out
+fn binary_datetime(bytes: &[u8], date_only: bool) -> String {
+    let part = |index: usize| u32::from(bytes.get(index).copied().unwrap_or(0));
+    let year = part(1) | (part(2) << 8);
+    let date = format!("{:04}-{:02}-{:02}", year, part(3), part(4));
+    if date_only {
+        return date;
+    }
+    let micros = part(8) | (part(9) << 8) | (part(10) << 16) | (part(11) << 24);
+    format!("{} {:02}:{:02}:{:02}{}", date, part(5), part(6), part(7), fraction(micros))
+}
+
+/// Binary TIME: a length byte, sign, days (4 bytes LE), hour, minute, second and microseconds
+fn binary_time(bytes: &[u8]) -> String {
+    let part = |index: usize| u32::from(bytes.get(index).copied().unwrap_or(0));
+    let sign = if part(1) == 1 { "-" } else { "" };
+    let days = part(2) | (part(3) << 8) | (part(4) << 16) | (part(5) << 24);
+    let micros = part(9) | (part(10) << 8) | (part(11) << 16) | (part(12) << 24);
+    format!("{}{:02}:{:02}:{:02}{}", sign, days * 24 + part(6), part(7), part(8), fraction(micros))
+}
+
+fn fraction(micros: u32) -> String {
+    if micros == 0 { String::new() } else { format!(".{:06}", micros) }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{binary_datetime, binary_time};
+
+    #[test]
+    fn test_binary_temporals() {
+        assert_eq!(binary_datetime(&[4, 0xe8, 0x07, 2, 29], true), "2024-02-29");
+        assert_eq!(binary_datetime(&[7, 0xe8, 0x07, 2, 29, 13, 5, 9], false), "2024-02-29 13:05:09");
+        assert_eq!(binary_datetime(&[11, 0xe8, 0x07, 2, 29, 13, 5, 9, 0x40, 0xe2, 0x01, 0], false), "2024-02-29 13:05:09.123456");
+        assert_eq!(binary_datetime(&[0], false), "0000-00-00 00:00:00");
+        assert_eq!(binary_time(&[8, 1, 1, 0, 0, 0, 2, 30, 0]), "-26:30:00");
     }
 }
@@ -1,26 +1,39 @@
-use std::{path::Path, sync::Arc};
-use anyhow::{anyhow, Error};
-use arrow::array::{Array, BooleanArray, Float64Array, Int64Array, StringArray};
-use datafusion::{arrow::array::RecordBatch, prelude::SessionConfig};
-use diesel::result;
-use rust_csv::CsvFile;
+use std::{fs, io::Write, path::Path, sync::{Arc, OnceLock}};
+use arrow::{array::{Array, BooleanArray, Float64Array, Int64Array, StringArray}, util::display::{ArrayFormatter, FormatOptions}};
+use datafusion::{arrow::array::RecordBatch, datasource::file_format::file_compression_type::FileCompressionType};
 use async_trait::async_trait;
 use datafusion::prelude::*;
-use crate::trait_req_impl::csv_trait::CsvImplTrait;
+use tempfile::NamedTempFile;
+use crate::{configuration::{config_error::ConfigError, csv_config::{CsvCompression, CsvEncoding, CsvOptions}}, datasource::db_utill::{ColumnName, TableSchema}, error::TalkError, trait_req_impl::csv_trait::CsvImplTrait};
 
 pub struct CsvUtill{
     file_path: String,
-    table_name: String
+    /// Name the file is registered under in the query context
+    table_name: String,
+    options: CsvOptions,
+    /// Utf-8 copy of a latin1 file, written on first use and deleted with `self`
+    utf8_copy: OnceLock<NamedTempFile>,
 }
 
 impl CsvUtill{
 
     // new funtion that take file path and return self
     pub fn new(the_path: String) -> Self {
-        Self { 
+        Self::with_options(the_path, CsvOptions::default())
+    }
+
+    /// The table name comes from `options`, else from the file name
+    pub fn with_options(the_path: String, options: CsvOptions) -> Self {
+        Self {
+            table_name: options.table_name(&the_path),
             file_path: the_path,
-            table_name: "target".to_string()
-         }
+            options,
+            utf8_copy: OnceLock::new(),
+        }
+    }
+
+    pub fn table_name(&self) -> &str {
+        &self.table_name
     }
 
     // verify is file exist
@@ -29,14 +42,78 @@ impl CsvUtill{
         is_exist
     }
     
-     // Method to get the schema (first few rows) of the CSV file
-     pub fn get_schema(&self)  {
-        // Read the CSV file lazily
-        let csv_file = CsvFile::read(self.file_path.to_string()).unwrap();
-        let cols = csv_file.heads();
-        print!("{:?}",cols);
+    /// Column names as DataFusion reads them; `column_1` ... for files without a header
+    pub async fn get_columns(&self) -> Result<Vec<String>, TalkError> {
+        let ctx = self.context().await?;
+        let table = ctx.table(self.table_name.as_str()).await?;
+        let columns = table.schema().fields().iter().map(|field| field.name().to_string()).collect::<Vec<_>>();
+        if columns.is_empty() {
+            return Err(TalkError::Execution(format!("csv '{}' has no columns", self.file_path)));
+        }
+        Ok(columns)
     }
     
+    /// The file as the one table questions about it are asked against
+    pub async fn table_schema(&self) -> Result<TableSchema, TalkError> {
+        Ok(TableSchema {
+            table_name: self.table_name.clone(),
+            field_columns: self.get_columns().await?.iter().map(|column| ColumnName::new(column)).collect(),
+        })
+    }
+
+    /// A fresh query context with the file registered under `table_name`
+    async fn context(&self) -> Result<SessionContext, TalkError> {
+        let path = self.readable_path()?;
+        let compression = match self.options.compression(&path) {
+            CsvCompression::None => FileCompressionType::UNCOMPRESSED,
+            CsvCompression::Gzip => FileCompressionType::GZIP,
+            CsvCompression::Zstd => FileCompressionType::ZSTD,
+        };
+        let mut read = CsvReadOptions::new()
+            .has_header(self.options.has_header())
+            .delimiter(self.options.delimiter())
+            .quote(self.options.quote())
+            // the path names one file, so there is nothing to filter by extension
+            .file_extension("")
+            .file_compression_type(compression);
+        if let Some(escape) = self.options.escape() {
+            read = read.escape(escape);
+        }
+        if let Some(null_regex) = self.options.null_regex() {
+            read = read.null_regex(Some(null_regex));
+        }
+
+        let ctx = SessionContext::new();
+        ctx.register_csv(self.table_name.as_str(), &path, read).await?;
+        Ok(ctx)
+    }
+
+    /// DataFusion reads utf-8 only; a latin1 file is read through a utf-8 copy
+    fn readable_path(&self) -> Result<String, TalkError> {
+        if self.options.encoding() == CsvEncoding::Utf8 {
+            return Ok(self.file_path.clone());
+        }
+        if self.options.compression(&self.file_path) != CsvCompression::None {
+            return Err(TalkError::Config(ConfigError::single(
+                "csv.encoding",
+                format!("'{}' is compressed, only utf8 is supported for compressed files", self.file_path),
+                "decompress the file or convert it to utf-8",
+            )));
+        }
+        if let Some(copy) = self.utf8_copy.get() {
+            return Ok(copy.path().to_string_lossy().to_string());
+        }
+        let bytes = fs::read(&self.file_path)
+            .map_err(|err| TalkError::Execution(format!("failed to read csv '{}': {}", self.file_path, err)))?;
+        // every latin1 byte is the unicode code point of the same value
+        let text: String = bytes.iter().map(|&byte| byte as char).collect();
+        let write_failed = |err: std::io::Error| TalkError::Execution(format!("failed to write utf-8 copy of '{}': {}", self.file_path, err));
+        let mut copy = tempfile::Builder::new().prefix("talk_with_db-").suffix(".csv").tempfile().map_err(write_failed)?;
+        copy.write_all(text.as_bytes()).and_then(|_| copy.flush()).map_err(write_failed)?;
+        // a copy written concurrently by another query loses the race and is deleted on drop
+        Ok(self.utf8_copy.get_or_init(|| copy).path().to_string_lossy().to_string())
+    }
+
     pub fn record_batches_to_string(batches: Vec<RecordBatch>) -> String {
         let mut output = String::new();
     
@@ -85,7 +162,11 @@ impl CsvUtill{
             return arr.value(index).to_string();
         }
     
-        "[unsupported type]".to_string()
+        // dates, decimals and the rest in arrow's own display form
+        match ArrayFormatter::try_new(array.as_ref(), &FormatOptions::default()) {
+            Ok(formatter) => formatter.value(index).to_string(),
+            Err(_) => "[unsupported type]".to_string(),
+        }
     }
     // perform query
 
@@ -94,47 +175,67 @@ impl CsvUtill{
 
 #[async_trait]
 impl CsvImplTrait for CsvUtill {
-    async fn execute_csv_query(&self, the_query: String) -> String {
-    let mut ctx = SessionContext::new();
-
-    ctx.register_csv(
-        "products",
-        self.file_path.to_string(),
-        CsvReadOptions::new()
-            .has_header(true)
-            .delimiter(b',')
-    ).await.unwrap();
-
-    let df = ctx.sql(&the_query)
-        .await
-        .expect("Failed to execute query");
-
-    let results = df.collect()
-        .await
-        .expect("Failed to collect results");
-
-    // Use the helper to convert RecordBatches to string
-    Self::record_batches_to_string(results)
+    async fn execute_csv_query(&self, the_query: String) -> Result<Vec<RecordBatch>, TalkError> {
+    let ctx = self.context().await?;
+
+    let df = ctx.sql(&the_query).await?;
+
+    let results = df.collect().await?;
+
+    Ok(results)
 } 
 }
 
 
 #[cfg(test)]
 pub mod test {
-    use crate::trait_req_impl::csv_trait::CsvImplTrait;
+    use crate::{configuration::csv_config::{CsvEncoding, CsvOptions}, error::TalkError, trait_req_impl::csv_trait::CsvImplTrait};
     use super::CsvUtill;
 
     #[tokio::test]
     pub async fn test_csv_read() {
-        let path = "/home/otterdev_ball/BaseDiskProject/rust_project/all_new_talk_with_db/products-100.csv";
-        let csv_utill = CsvUtill::new(path.to_string());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("products-100.csv");
+        std::fs::write(&path, "index,name,price\n1,lamp,19.5\n2,desk,120\n3,chair,45\n").unwrap();
+        let csv_utill = CsvUtill::new(path.to_string_lossy().to_string());
 
-        let result_string = csv_utill.execute_csv_query("SELECT * FROM products WHERE price < 100".to_string()).await;
+        let batches = csv_utill.execute_csv_query("SELECT * FROM products_100 WHERE price < 100".to_string()).await.unwrap();
+        let result_string = CsvUtill::record_batches_to_string(batches);
+        assert!(result_string.contains("price"), "Expected output to include header 'price'");
+    }
 
-        // Just print the output to visually confirm
-        println!("{}", result_string);
+    #[tokio::test]
+    async fn test_typed_rows_and_unknown_column() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("csv_utill_typed_rows.csv");
+        std::fs::write(&path, "name,price\nlamp,19.5\ndesk,120\n").unwrap();
+        let options = CsvOptions { table_name: Some("products".to_string()), ..Default::default() };
+        let csv_utill = CsvUtill::with_options(path.to_string_lossy().to_string(), options);
+
+        let result = csv_utill.execute_csv_typed("SELECT name, price FROM products WHERE price < 100".to_string()).await.unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result.rows[0][1], serde_json::json!(19.5));
+
+        let err = csv_utill.execute_csv_query("SELECT colour FROM products".to_string()).await.unwrap_err();
+        assert!(matches!(err, TalkError::Execution(_)), "{}", err);
+    }
 
-        // Optional: Add a simple assertion
-        assert!(result_string.contains("price"), "Expected output to include header 'price'");
+    #[tokio::test]
+    async fn test_delimiter_nulls_and_latin1() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Caf\u{e9} Orders.csv");
+        std::fs::write(&path, b"city;total\nM\xfcnchen;12\nGen\xe8ve;NA\n").unwrap();
+        let options = CsvOptions {
+            delimiter: Some(';'),
+            encoding: Some(CsvEncoding::Latin1),
+            null_values: vec!["NA".to_string()],
+            ..Default::default()
+        };
+        let csv_utill = CsvUtill::with_options(path.to_string_lossy().to_string(), options);
+        assert_eq!(csv_utill.table_name(), "caf__orders");
+
+        let result = csv_utill.execute_csv_typed("SELECT city, total FROM caf__orders ORDER BY city".to_string()).await.unwrap();
+        assert_eq!(result.rows[0], vec![serde_json::json!("Genève"), serde_json::Value::Null]);
+        assert_eq!(result.rows[1][0], serde_json::json!("München"));
     }
 }
\ No newline at end of file
@@ -1,35 +1,22 @@
 use std::collections::HashMap;
 use std::fmt;
 
-use diesel::prelude::*;
-use diesel::{Connection, MysqlConnection, QueryResult, RunQueryDsl};
-use diesel::sql_query;
-use diesel::sql_types::Text;
-use anyhow::{Error, Result};
-use mysql::prelude::Queryable;
-use mysql::{Opts, Pool, Row};
 use serde::{Deserialize, Serialize};
-use anyhow::anyhow;
+use utoipa::ToSchema;
 
-use crate::configuration::db_config::DatabaseConfig;
+use crate::{configuration::{app_config::AppConfig, load_config::connection_options::ConnectionOptions}, datasource::async_db_utill::AsyncDb, error::TalkResult};
 
+/// Schema introspection and ad hoc queries on one pool
 pub struct DbUtil {
-    pub db_con: MysqlConnection,
+    pub pool: AsyncDb,
 }
 
-#[derive(Debug, QueryableByName, Deserialize, Serialize, Clone)]
-pub struct TableName {
-    #[diesel(sql_type = Text)]
-    table_name: String,
-}
-
-#[derive(Debug, QueryableByName, Deserialize, Serialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
 pub struct ColumnName {
-    #[diesel(sql_type = Text)]
     column_name: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct TableSchema {
     pub table_name: String,
     pub field_columns: Vec<ColumnName>,
@@ -44,7 +31,7 @@ impl Default for TableSchema {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct DatabaseSchema {
     pub schemas: Vec<TableSchema>,
 }
@@ -69,6 +56,12 @@ impl fmt::Display for DatabaseSchema {
     }
 }
 
+impl ColumnName {
+    pub fn new(column_name: &str) -> Self {
+        Self { column_name: column_name.to_string() }
+    }
+}
+
 impl fmt::Display for ColumnName {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.column_name)
@@ -76,183 +69,78 @@ impl fmt::Display for ColumnName {
 }
 
 impl DbUtil {
-    /// Construct a new DbUtil with a fresh MySQL connection
-    pub fn new() -> Result<Self> {
-        let db_config = crate::configuration::db_config::DatabaseConfig::inject_from_env();
-        let connection = MysqlConnection::establish(&db_config.db_url)?;
-        Ok(Self {
-            db_con: connection,
-        })
+    /// Construct a new DbUtil with a pool for the configured database
+    pub async fn new(config: &AppConfig) -> TalkResult<Self> {
+        Ok(Self { pool: AsyncDb::new(config).await? })
     }
 
-    /// Collect full schema: tables + columns
-    pub fn get_database_schema(&mut self) -> DatabaseSchema {
-        let mut database_schema = DatabaseSchema::default();
-
-        let all_tables = match self.get_all_tables() {
-            Ok(tables) => tables,
-            Err(err) => {
-                eprintln!("Failed to retrieve tables: {}", err);
-                return database_schema;
-            }
-        };
-
-        for table_name in all_tables.iter() {
-            let mut table_schema = TableSchema {
-                table_name: table_name.to_string(),
-                ..Default::default()
-            };
-
-            match self.get_columns_for_table(table_name) {
-                Ok(columns) => {
-                    table_schema.field_columns = columns
-                        .into_iter()
-                        .map(|col_name| ColumnName {
-                            column_name: col_name,
-                        })
-                        .collect();
-                }
-                Err(err) => {
-                    eprintln!("Failed to get columns for table '{}': {}", table_name, err);
-                    continue;
-                }
-            }
-
-            database_schema.schemas.push(table_schema);
-        }
-
-        database_schema
+    pub async fn connect(db_url: &str) -> TalkResult<Self> {
+        Self::connect_with(db_url, &ConnectionOptions::default()).await
     }
 
-    /// Get all table names in the current database schema
-    fn get_all_tables(&mut self) -> QueryResult<Vec<String>> {
-        let db_name = "Chinook";
-        let query = format!(
-            "SELECT table_name AS `table_name` FROM information_schema.tables WHERE table_schema = '{}'",
-            db_name
-        );
-
-        let results: Vec<TableName> = sql_query(query).load(&mut self.db_con)?;
-        Ok(results.into_iter().map(|r| r.table_name).collect())
+    pub async fn connect_with(db_url: &str, options: &ConnectionOptions) -> TalkResult<Self> {
+        Ok(Self { pool: AsyncDb::connect_with(db_url, options).await? })
     }
 
-    /// Get all columns (fields) for a specific table
-    fn get_columns_for_table(&mut self, table_name: &str) -> QueryResult<Vec<String>> {
-        let db_name = "Chinook";
-        let query = format!(
-            "SELECT column_name AS `column_name` FROM information_schema.columns WHERE table_schema = '{}' AND table_name = '{}'",
-            db_name, table_name
-        );
-
-        let results: Vec<ColumnName> = sql_query(query).load(&mut self.db_con)?;
-        Ok(results.into_iter().map(|r| r.column_name).collect())
+    /// Collect full schema: tables + columns
+    pub async fn get_database_schema(&self) -> TalkResult<DatabaseSchema> {
+        Ok(self.pool.database_schema().await?)
     }
 
+    pub async fn table_names(&self) -> TalkResult<Vec<String>> {
+        Ok(self.pool.table_names().await?)
+    }
 
-    pub async fn query(&self, query: &str) -> Result<Vec<HashMap<String, String>>, Box<dyn std::error::Error>>{
-        let url = DatabaseConfig::inject_from_env(); // adjust as needed
-        let db_option = Opts::from_url(&url.db_url).unwrap();
-        let pool = Pool::new(db_option)?;
-        let mut conn = pool.get_conn()?;
-    
-        let result: Vec<Row> = conn.query(query)?;
-    
-        let mut rows: Vec<HashMap<String, String>> = Vec::new();
-    
-        for row in result {
-            let mut map = HashMap::new();
-            for (i, col) in row.columns_ref().iter().enumerate() {
-                let key = col.name_str().to_string();
-                let value = row.as_ref(i).map(|v| Self::value_to_string(v)).unwrap_or_default();
-
-                map.insert(key, value);
-            }
-            rows.push(map);
-        }
-    
-        Ok(rows)
+    /// Columns of one table, or `None` when the table does not exist.
+    /// The name is only ever bound as a parameter.
+    pub async fn describe_table(&self, table_name: &str) -> TalkResult<Option<TableSchema>> {
+        Ok(self.pool.describe_table(table_name).await?)
     }
-    fn value_to_string(val: &mysql::Value) -> String {
-        match val {
-            mysql::Value::NULL => "NULL".to_string(),
-            mysql::Value::Bytes(bytes) => String::from_utf8_lossy(bytes).to_string(),
-            mysql::Value::Int(i) => i.to_string(),
-            mysql::Value::UInt(u) => u.to_string(),
-            mysql::Value::Float(f) => f.to_string(),
-            mysql::Value::Double(d) => d.to_string(),
-            mysql::Value::Date(y, m, d, h, min, s, micros) => {
-                format!(
-                    "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:06}",
-                    y, m, d, h, min, s, micros
-                )
-            }
-            mysql::Value::Time(is_neg, d, h, m, s, micros) => {
-                let sign = if *is_neg { "-" } else { "" };
-                // Dereferencing `d` and `h` here:
-                let total_hours = (*d as u32) * 24 + (*h as u32);
-                format!("{sign}{:03}:{:02}:{:02}.{:06}", total_hours, m, s, micros)
-            }
-        }
+
+    pub async fn query(&self, query: &str) -> TalkResult<Vec<HashMap<String, String>>> {
+        Ok(self.pool.query(query, None).await?)
     }
 
-    pub async fn query_as_string(&self, generated_query: String) -> Result<String, Error> {
-        let db_util = match DbUtil::new() {
-            Ok(db_util) => db_util,
-            Err(_) => return Err(anyhow!("fail to connect to db")),
-        };
-    
-        let result = db_util.query(&generated_query).await.unwrap();
-    
-        // Convert the Vec<HashMap<String, String>> into a formatted string
-        let mut output = String::new();
-        for (i, row) in result.iter().enumerate() {
-            output.push_str(&format!("Row {}:\n", i + 1));
-            for (key, value) in row {
-                output.push_str(&format!("  {}: {}\n", key, value));
-            }
-        }
-    
-        Ok(output)
+    pub async fn query_as_string(&self, generated_query: String) -> TalkResult<String> {
+        Ok(self.pool.query_as_string(generated_query).await?)
     }
-    
 }
 
 
-
-
-
 #[cfg(test)]
 mod test {
-    use super::DbUtil;
+    use super::{AppConfig, DbUtil};
 
-    #[test]
-    fn test_database_tables_and_columns() {
-        let mut db_util = DbUtil::new().unwrap();
-        let result_tables = db_util.get_all_tables().unwrap();
+    async fn db_util() -> DbUtil {
+        DbUtil::new(&AppConfig::load(None).unwrap()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_database_tables_and_columns() {
+        let db_util = db_util().await;
+        let result_tables = db_util.table_names().await.unwrap();
 
         for table in result_tables.iter() {
             println!("Table: {:?}", table);
-            let columns = db_util.get_columns_for_table(table).unwrap();
-            for column in columns.iter() {
+            let columns = db_util.describe_table(table).await.unwrap().unwrap();
+            for column in columns.field_columns.iter() {
                 println!("  Column: {}", column);
             }
         }
     }
 
-    #[test]
-    fn test_database_schema_struct() {
-        let mut db_util = DbUtil::new().unwrap();
-        let schema = db_util.get_database_schema();
+    #[tokio::test]
+    async fn test_database_schema_struct() {
+        let db_util = db_util().await;
+        let schema = db_util.get_database_schema().await.unwrap();
         println!("{:#?}", schema);
     }
 
     #[tokio::test]
     async fn test_query() {
-        let db_util = DbUtil::new().unwrap();
+        let db_util = db_util().await;
         let sql = "SELECT table_name FROM information_schema.tables WHERE table_schema = 'Chinook';";
         let result = db_util.query(sql).await.unwrap();
         print!("{:?}", result);
     }
-
-
 }
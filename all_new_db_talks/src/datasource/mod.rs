@@ -1,3 +1,8 @@
 pub mod db_utill;
 pub mod async_db_utill;
-pub mod csv_utill;
\ No newline at end of file
+pub mod csv_utill;
+pub mod session_store;
+pub mod result_set;
+pub mod api_key_store;
+pub mod connection;
+pub mod pool;
\ No newline at end of file
@@ -0,0 +1,128 @@
+use std::{
+    future::Future,
+    sync::{Arc, Weak},
+    time::{Duration, Instant},
+};
+
+use anyhow::Error;
+use serde::Serialize;
+use tokio::sync::RwLock;
+use utoipa::ToSchema;
+
+use crate::{
+    configuration::{load_config::connection_options::ConnectionOptions, secret::Secret},
+    datasource::async_db_utill::AsyncDb,
+};
+
+/// Used when a datasource sets no `health_check_secs`
+pub const DEFAULT_HEALTH_CHECK_SECS: u64 = 30;
+
+/// Outcome of the last ping of a pool
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PoolHealth {
+    pub healthy: bool,
+    /// Round trip of the ping in milliseconds
+    pub latency_ms: u64,
+    /// Times the pool was rebuilt since startup
+    pub reconnects: u64,
+}
+
+/// The connections in use and how many times they were rebuilt
+struct Current {
+    db: AsyncDb,
+    generation: u64,
+}
+
+struct PoolInner {
+    url: Secret,
+    options: ConnectionOptions,
+    current: RwLock<Current>,
+}
+
+/// The one pool of a datasource, created at startup and shared by schema
+/// introspection and query execution. When the server cannot be reached the
+/// pool is rebuilt, both by the background health check and on demand.
+#[derive(Clone)]
+pub struct SharedPool {
+    inner: Arc<PoolInner>,
+}
+
+impl SharedPool {
+    /// Open the pool and check it answers; starts the background health check
+    pub async fn connect(url: Secret, options: ConnectionOptions) -> Result<Self, Error> {
+        let db = AsyncDb::connect_with(url.expose(), &options).await?;
+        db.ping().await?;
+        let pool = Self {
+            inner: Arc::new(PoolInner { url, options, current: RwLock::new(Current { db, generation: 0 }) }),
+        };
+        let secs = pool.inner.options.health_check_secs.unwrap_or(DEFAULT_HEALTH_CHECK_SECS);
+        if secs > 0 {
+            spawn_health_checks(Arc::downgrade(&pool.inner), Duration::from_secs(secs));
+        }
+        Ok(pool)
+    }
+
+    /// Handle on the current connections, with the generation they belong to
+    pub async fn db(&self) -> (AsyncDb, u64) {
+        let current = self.inner.current.read().await;
+        (current.db.clone(), current.generation)
+    }
+
+    /// Replace the pool of generation `failed` with a freshly connected one.
+    /// Callers that failed at the same time wait on the write lock and then find
+    /// the pool already replaced, so only the first one reconnects.
+    pub async fn reconnect(&self, failed: u64) -> Result<(), Error> {
+        let mut current = self.inner.current.write().await;
+        if current.generation != failed {
+            return Ok(());
+        }
+        let db = AsyncDb::connect_with(self.inner.url.expose(), &self.inner.options).await?;
+        db.ping().await?;
+        *current = Current { db, generation: failed + 1 };
+        Ok(())
+    }
+
+    /// Run `op` on the pool; when it fails because the server went away,
+    /// reconnect and run it once more
+    pub async fn run<T, F, Fut>(&self, op: F) -> Result<T, Error>
+    where
+        F: Fn(AsyncDb) -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+    {
+        let (db, generation) = self.db().await;
+        match op(db).await {
+            Err(err) if AsyncDb::is_connection_error(&err) => {
+                self.reconnect(generation).await.map_err(|reconnect| err.context(format!("reconnect failed: {}", reconnect)))?;
+                op(self.db().await.0).await
+            }
+            result => result,
+        }
+    }
+
+    /// Ping the pool, rebuilding it when the ping fails
+    pub async fn check(&self) -> PoolHealth {
+        let started = Instant::now();
+        let healthy = self.run(|db| async move { db.ping().await }).await.is_ok();
+        PoolHealth {
+            healthy,
+            latency_ms: started.elapsed().as_millis() as u64,
+            reconnects: self.inner.current.read().await.generation,
+        }
+    }
+}
+
+/// Ends once the pool is dropped
+fn spawn_health_checks(inner: Weak<PoolInner>, every: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(every);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let Some(inner) = inner.upgrade() else { break };
+            let health = SharedPool { inner }.check().await;
+            if !health.healthy {
+                eprintln!("Database pool unhealthy, will retry in {}s", every.as_secs());
+            }
+        }
+    });
+}
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+use arrow::{
+    array::{Array, RecordBatch},
+    datatypes::DataType,
+    error::ArrowError,
+    util::display::{ArrayFormatter, FormatOptions},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnKind {
+    Integer,
+    Float,
+    /// Exact numbers, kept as strings so no precision is lost
+    Decimal,
+    Boolean,
+    Text,
+    Date,
+    DateTime,
+    Time,
+    Json,
+    Binary,
+    Null,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ResultColumn {
+    pub name: String,
+    pub data_type: ColumnKind,
+}
+
+/// Rows of a query with typed columns, in the order the database returned them
+#[derive(Debug, Clone, Serialize, Deserialize, Default, ToSchema)]
+pub struct ResultSet {
+    pub columns: Vec<ResultColumn>,
+    /// One array per row, values in column order
+    #[schema(value_type = Vec<Vec<Object>>)]
+    pub rows: Vec<Vec<Value>>,
+}
+
+impl ResultSet {
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn truncate(&mut self, limit: usize) {
+        self.rows.truncate(limit);
+    }
+
+    /// Row maps of display strings, the shape the summarizer and text output use
+    pub fn to_string_rows(&self) -> Vec<HashMap<String, String>> {
+        self.rows
+            .iter()
+            .map(|row| {
+                self.columns
+                    .iter()
+                    .zip(row)
+                    .map(|(column, value)| (column.name.clone(), Self::value_to_string(value)))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn value_to_string(value: &Value) -> String {
+        match value {
+            Value::Null => "NULL".to_string(),
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        }
+    }
+
+    /// Rows of arrow batches, e.g. a csv query; cells go through their display form
+    /// like database values, so both sources type their columns the same way
+    pub fn from_batches(batches: &[RecordBatch]) -> Result<Self, ArrowError> {
+        let Some(first) = batches.first() else {
+            return Ok(Self::default());
+        };
+        let columns: Vec<ResultColumn> = first
+            .schema()
+            .fields()
+            .iter()
+            .map(|field| ResultColumn { name: field.name().to_string(), data_type: Self::arrow_kind(field.data_type()) })
+            .collect();
+
+        let options = FormatOptions::default();
+        let mut rows = Vec::new();
+        for batch in batches {
+            let formatters = batch
+                .columns()
+                .iter()
+                .map(|array| ArrayFormatter::try_new(array.as_ref(), &options))
+                .collect::<Result<Vec<_>, _>>()?;
+            for row in 0..batch.num_rows() {
+                let values = columns
+                    .iter()
+                    .zip(batch.columns().iter().zip(&formatters))
+                    .map(|(column, (array, formatter))| match array.is_null(row) {
+                        true => Value::Null,
+                        false => Self::typed_value(column.data_type, &formatter.value(row).to_string()),
+                    })
+                    .collect();
+                rows.push(values);
+            }
+        }
+        Ok(Self { columns, rows })
+    }
+
+    fn arrow_kind(data_type: &DataType) -> ColumnKind {
+        match data_type {
+            DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64
+            | DataType::UInt8 | DataType::UInt16 | DataType::UInt32 | DataType::UInt64 => ColumnKind::Integer,
+            DataType::Float16 | DataType::Float32 | DataType::Float64 => ColumnKind::Float,
+            DataType::Decimal128(_, _) | DataType::Decimal256(_, _) => ColumnKind::Decimal,
+            DataType::Boolean => ColumnKind::Boolean,
+            DataType::Date32 | DataType::Date64 => ColumnKind::Date,
+            DataType::Timestamp(_, _) => ColumnKind::DateTime,
+            DataType::Time32(_) | DataType::Time64(_) => ColumnKind::Time,
+            DataType::Binary | DataType::LargeBinary | DataType::BinaryView | DataType::FixedSizeBinary(_) => ColumnKind::Binary,
+            DataType::Null => ColumnKind::Null,
+            _ => ColumnKind::Text,
+        }
+    }
+
+    /// Parse a textual cell into the json value for its column kind.
+    /// Values that do not parse stay strings rather than failing the whole result.
+    pub fn typed_value(kind: ColumnKind, text: &str) -> Value {
+        match kind {
+            ColumnKind::Integer => text
+                .parse::<i64>()
+                .map(Value::from)
+                .or_else(|_| text.parse::<u64>().map(Value::from))
+                .unwrap_or_else(|_| Value::String(text.to_string())),
+            ColumnKind::Float => text
+                .parse::<f64>()
+                .ok()
+                .and_then(|number| serde_json::Number::from_f64(number).map(Value::Number))
+                .unwrap_or_else(|| Value::String(text.to_string())),
+            ColumnKind::Boolean => match text {
+                "1" | "true" | "TRUE" => Value::Bool(true),
+                "0" | "false" | "FALSE" => Value::Bool(false),
+                _ => Value::String(text.to_string()),
+            },
+            ColumnKind::Json => serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string())),
+            ColumnKind::Null => Value::Null,
+            _ => Value::String(text.to_string()),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use arrow::{
+        array::{Float64Array, RecordBatch, StringArray},
+        datatypes::{DataType, Field, Schema},
+    };
+    use serde_json::json;
+
+    use super::{ColumnKind, ResultColumn, ResultSet};
+
+    #[test]
+    fn test_typed_values_and_string_rows() {
+        let result = ResultSet {
+            columns: vec![
+                ResultColumn { name: "name".to_string(), data_type: ColumnKind::Text },
+                ResultColumn { name: "total".to_string(), data_type: ColumnKind::Integer },
+            ],
+            rows: vec![vec![json!("AC/DC"), ResultSet::typed_value(ColumnKind::Integer, "42")]],
+        };
+        assert_eq!(result.rows[0][1], json!(42));
+        assert_eq!(result.to_string_rows()[0]["total"], "42");
+        assert_eq!(ResultSet::typed_value(ColumnKind::Float, "abc"), json!("abc"));
+    }
+
+    #[test]
+    fn test_from_batches() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("name", DataType::Utf8, false),
+            Field::new("price", DataType::Float64, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(StringArray::from(vec!["lamp", "desk"])),
+                Arc::new(Float64Array::from(vec![Some(19.5), None])),
+            ],
+        )
+        .unwrap();
+        let result = ResultSet::from_batches(&[batch]).unwrap();
+        assert_eq!(result.columns[1].data_type, ColumnKind::Float);
+        assert_eq!(result.rows, vec![vec![json!("lamp"), json!(19.5)], vec![json!("desk"), json!(null)]]);
+    }
+}
@@ -0,0 +1,211 @@
+use anyhow::{anyhow, Error, Result};
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{BigInt, Integer, Nullable, Text};
+use diesel::{Connection, RunQueryDsl, SqliteConnection};
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use serde::{Deserialize, Serialize};
+
+use crate::agent::session::{ConversationSession, ConversationTurn, DEFAULT_HISTORY_TOKEN_BUDGET};
+
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+/// Keeps conversation sessions in a local SQLite file
+pub struct SessionStore {
+    pub db_con: SqliteConnection,
+}
+
+#[derive(Debug, QueryableByName, Deserialize, Serialize, Clone)]
+pub struct SessionSummary {
+    #[diesel(sql_type = Text)]
+    pub id: String,
+    #[diesel(sql_type = Text)]
+    pub created_at: String,
+    #[diesel(sql_type = Text)]
+    pub updated_at: String,
+    #[diesel(sql_type = BigInt)]
+    pub turn_count: i64,
+}
+
+#[derive(Debug, QueryableByName)]
+struct SessionTurnRow {
+    #[diesel(sql_type = Text)]
+    question: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    generated_sql: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    summary: Option<String>,
+    #[diesel(sql_type = Integer)]
+    attempts: i32,
+    #[diesel(sql_type = Nullable<BigInt>)]
+    duration_ms: Option<i64>,
+    #[diesel(sql_type = Nullable<Text>)]
+    result_preview: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    feedback: Option<String>,
+}
+
+#[derive(Debug, QueryableByName)]
+struct SessionRow {
+    #[diesel(sql_type = Text)]
+    id: String,
+    #[diesel(sql_type = Nullable<Text>)]
+    owner: Option<String>,
+}
+
+impl SessionStore {
+    /// Open (or create) the SQLite file and run pending migrations
+    pub fn open(db_path: &str) -> Result<Self> {
+        let mut connection = SqliteConnection::establish(db_path)?;
+        sql_query("PRAGMA foreign_keys = ON").execute(&mut connection)?;
+        connection
+            .run_pending_migrations(MIGRATIONS)
+            .map_err(|err| anyhow!("failed to migrate session store '{}': {}", db_path, err))?;
+        Ok(Self { db_con: connection })
+    }
+
+    /// Insert or replace the session and all of its turns
+    pub fn save(&mut self, session: &ConversationSession) -> Result<()> {
+        self.db_con.transaction::<_, Error, _>(|conn| {
+            sql_query(
+                "INSERT INTO sessions (id, owner) VALUES (?, ?)
+                 ON CONFLICT(id) DO UPDATE SET updated_at = datetime('now')",
+            )
+            .bind::<Text, _>(&session.id)
+            .bind::<Nullable<Text>, _>(session.owner.as_deref())
+            .execute(conn)?;
+
+            sql_query("DELETE FROM session_turns WHERE session_id = ?")
+                .bind::<Text, _>(&session.id)
+                .execute(conn)?;
+
+            for (index, turn) in session.turns.iter().enumerate() {
+                sql_query(
+                    "INSERT INTO session_turns
+                     (session_id, turn_index, question, generated_sql, summary, attempts, duration_ms, result_preview, feedback)
+                     VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                )
+                .bind::<Text, _>(&session.id)
+                .bind::<Integer, _>(index as i32)
+                .bind::<Text, _>(&turn.question)
+                .bind::<Nullable<Text>, _>(turn.sql.as_deref())
+                .bind::<Nullable<Text>, _>(turn.summary.as_deref())
+                .bind::<Integer, _>(turn.attempts as i32)
+                .bind::<Nullable<BigInt>, _>(turn.duration_ms.map(|ms| ms as i64))
+                .bind::<Nullable<Text>, _>(turn.result_preview.as_deref())
+                .bind::<Nullable<Text>, _>(turn.feedback.as_deref())
+                .execute(conn)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// All sessions, most recently updated first
+    pub fn list(&mut self) -> Result<Vec<SessionSummary>> {
+        let sessions = sql_query(
+            "SELECT s.id, s.created_at, s.updated_at, COUNT(t.id) AS turn_count
+             FROM sessions s LEFT JOIN session_turns t ON t.session_id = s.id
+             GROUP BY s.id, s.created_at, s.updated_at
+             ORDER BY s.updated_at DESC",
+        )
+        .load::<SessionSummary>(&mut self.db_con)?;
+        Ok(sessions)
+    }
+
+    /// Load a session to resume it, `None` when the id is unknown
+    pub fn load(&mut self, session_id: &str) -> Result<Option<ConversationSession>> {
+        let found: Vec<SessionRow> = sql_query("SELECT id, owner FROM sessions WHERE id = ?")
+            .bind::<Text, _>(session_id)
+            .load(&mut self.db_con)?;
+        let Some(found) = found.into_iter().next() else {
+            return Ok(None);
+        };
+
+        let rows: Vec<SessionTurnRow> = sql_query(
+            "SELECT question, generated_sql, summary, attempts, duration_ms, result_preview, feedback
+             FROM session_turns WHERE session_id = ? ORDER BY turn_index",
+        )
+        .bind::<Text, _>(session_id)
+        .load(&mut self.db_con)?;
+
+        let turns = rows
+            .into_iter()
+            .map(|row| ConversationTurn {
+                question: row.question,
+                sql: row.generated_sql,
+                summary: row.summary,
+                attempts: row.attempts.max(0) as u32,
+                duration_ms: row.duration_ms.map(|ms| ms.max(0) as u64),
+                result_preview: row.result_preview,
+                feedback: row.feedback,
+            })
+            .collect();
+
+        Ok(Some(ConversationSession {
+            id: found.id,
+            turns,
+            token_budget: DEFAULT_HISTORY_TOKEN_BUDGET,
+            owner: found.owner,
+        }))
+    }
+
+    /// Returns false when there was nothing to delete
+    pub fn delete(&mut self, session_id: &str) -> Result<bool> {
+        self.db_con.transaction::<_, Error, _>(|conn| {
+            sql_query("DELETE FROM session_turns WHERE session_id = ?")
+                .bind::<Text, _>(session_id)
+                .execute(conn)?;
+            let deleted = sql_query("DELETE FROM sessions WHERE id = ?")
+                .bind::<Text, _>(session_id)
+                .execute(conn)?;
+            Ok(deleted > 0)
+        })
+    }
+
+    /// Attach user feedback to one turn (0-based index)
+    pub fn set_feedback(&mut self, session_id: &str, turn_index: usize, feedback: &str) -> Result<()> {
+        let updated = sql_query("UPDATE session_turns SET feedback = ? WHERE session_id = ? AND turn_index = ?")
+            .bind::<Text, _>(feedback)
+            .bind::<Text, _>(session_id)
+            .bind::<Integer, _>(turn_index as i32)
+            .execute(&mut self.db_con)?;
+        if updated == 0 {
+            return Err(anyhow!("session '{}' has no turn {}", session_id, turn_index));
+        }
+        Ok(())
+    }
+
+    /// Pretty printed JSON of the whole session
+    pub fn export(&mut self, session_id: &str) -> Result<String> {
+        let session = self
+            .load(session_id)?
+            .ok_or_else(|| anyhow!("session '{}' not found", session_id))?;
+        Ok(serde_json::to_string_pretty(&session)?)
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::SessionStore;
+    use crate::agent::session::ConversationSession;
+
+    #[test]
+    fn test_save_load_delete_session() {
+        let mut store = SessionStore::open(":memory:").unwrap();
+        let mut session = ConversationSession::owned_by(Some("reporting"));
+        session.record("total sales per year", Some("SELECT 1".to_string()), Some("42".to_string()));
+        store.save(&session).unwrap();
+        store.set_feedback(&session.id, 0, "good").unwrap();
+
+        let loaded = store.load(&session.id).unwrap().unwrap();
+        assert_eq!(loaded.turns.len(), 1);
+        assert_eq!(loaded.turns[0].feedback.as_deref(), Some("good"));
+        assert_eq!(loaded.owner.as_deref(), Some("reporting"));
+        assert_eq!(store.list().unwrap()[0].turn_count, 1);
+        assert!(store.export(&session.id).unwrap().contains("total sales per year"));
+
+        assert!(store.delete(&session.id).unwrap());
+        assert!(store.load(&session.id).unwrap().is_none());
+    }
+}
@@ -0,0 +1,137 @@
+use std::fmt;
+
+use datafusion::{arrow::error::ArrowError, error::DataFusionError};
+
+use crate::{
+    agent::{access_policy::AccessDenied, sql_validation::ValidationFinding},
+    configuration::config_error::ConfigError,
+};
+
+/// Every way answering a question can fail, so callers can react per kind
+/// (retry on `Timeout`, show findings on `Validation`, ...) instead of parsing messages
+#[derive(Debug)]
+pub enum TalkError {
+    Config(ConfigError),
+    /// The database could not be reached
+    Connection(String),
+    /// No model produced an answer
+    Llm(String),
+    /// The model answered, but no sql could be taken from its answer
+    Extraction(String),
+    /// The sql was refused before it reached the database
+    Validation(Vec<ValidationFinding>),
+    /// The api key may not do this
+    Policy(String),
+    /// The database or the csv engine rejected the query
+    Execution(String),
+    Timeout(String),
+}
+
+pub type TalkResult<T> = Result<T, TalkError>;
+
+impl fmt::Display for TalkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TalkError::Config(error) => write!(f, "{}", error),
+            TalkError::Validation(findings) => {
+                let reasons: Vec<String> = findings.iter().map(|finding| finding.to_string()).collect();
+                write!(f, "refusing to execute sql: {}", reasons.join("; "))
+            }
+            TalkError::Connection(message)
+            | TalkError::Llm(message)
+            | TalkError::Extraction(message)
+            | TalkError::Policy(message)
+            | TalkError::Execution(message)
+            | TalkError::Timeout(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for TalkError {}
+
+impl From<ConfigError> for TalkError {
+    fn from(error: ConfigError) -> Self {
+        TalkError::Config(error)
+    }
+}
+
+impl From<sqlx::Error> for TalkError {
+    fn from(err: sqlx::Error) -> Self {
+        match err {
+            sqlx::Error::PoolTimedOut => TalkError::Timeout("timed out waiting for a database connection".to_string()),
+            sqlx::Error::Io(_) | sqlx::Error::Tls(_) | sqlx::Error::Protocol(_) | sqlx::Error::PoolClosed | sqlx::Error::WorkerCrashed => {
+                TalkError::Connection(format!("database connection failed: {}", err))
+            }
+            err => TalkError::Execution(format!("failed to execute sql: {}", err)),
+        }
+    }
+}
+
+impl From<DataFusionError> for TalkError {
+    fn from(err: DataFusionError) -> Self {
+        TalkError::Execution(format!("failed to query csv: {}", err))
+    }
+}
+
+impl From<ArrowError> for TalkError {
+    fn from(err: ArrowError) -> Self {
+        TalkError::Execution(format!("failed to read query result: {}", err))
+    }
+}
+
+impl From<AccessDenied> for TalkError {
+    fn from(denied: AccessDenied) -> Self {
+        match denied {
+            AccessDenied::Unauthenticated => TalkError::Policy("missing or unknown api key".to_string()),
+            AccessDenied::Forbidden(message) => TalkError::Policy(message),
+            AccessDenied::Unavailable(message) => TalkError::Connection(message),
+        }
+    }
+}
+
+/// Internals use `anyhow`; errors raised as a `TalkError` (or one of the typed
+/// sources above) keep their kind, anything else counts as an execution failure
+impl From<anyhow::Error> for TalkError {
+    fn from(err: anyhow::Error) -> Self {
+        let err = match err.downcast::<TalkError>() {
+            Ok(error) => return error,
+            Err(err) => err,
+        };
+        let err = match err.downcast::<ConfigError>() {
+            Ok(error) => return error.into(),
+            Err(err) => err,
+        };
+        let err = match err.downcast::<sqlx::Error>() {
+            Ok(error) => return error.into(),
+            Err(err) => err,
+        };
+        match err.downcast::<DataFusionError>() {
+            Ok(error) => error.into(),
+            Err(err) => TalkError::Execution(err.to_string()),
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use anyhow::anyhow;
+
+    use super::TalkError;
+    use crate::configuration::config_error::ConfigError;
+
+    #[test]
+    fn test_kind_survives_anyhow() {
+        let timeout: anyhow::Error = TalkError::Timeout("model 'llama3' timed out".to_string()).into();
+        assert!(matches!(TalkError::from(timeout), TalkError::Timeout(_)));
+
+        let config: anyhow::Error = ConfigError::single("LLM_URL", "is not set", "set it").into();
+        assert!(matches!(TalkError::from(config), TalkError::Config(_)));
+
+        let pool: anyhow::Error = sqlx::Error::PoolTimedOut.into();
+        assert!(matches!(TalkError::from(pool), TalkError::Timeout(_)));
+
+        let other = TalkError::from(anyhow!("no csv file is attached"));
+        assert_eq!(other.to_string(), "no csv file is attached");
+    }
+}
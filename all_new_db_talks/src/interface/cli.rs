@@ -0,0 +1,400 @@
+use std::{io::Write, sync::Arc};
+
+use anyhow::{anyhow, Error};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use rustyline::DefaultEditor;
+
+use crate::{
+    agent::{
+        access_policy::{generate_api_key, ApiKeyPolicy},
+        datasource_router::DatasourceRouter,
+        evaluation::{load_suite, run_suite},
+        limits::RateLimiter,
+        model_router::unreported,
+        streaming::{stream_ask, ChainEvent},
+        text_to_sql::{ChainResponse, TextToSqlChain},
+    },
+    configuration::{
+        app_config::AppConfig,
+        csv_config::CsvOptions,
+        datasource_config::DatasourceKind,
+        model_config::ModelSelect,
+    },
+    datasource::api_key_store::ApiKeyStore,
+    interface::{
+        confirm::{confirm_sql, print_generated, ConfirmDecision},
+        mcp::{self, McpAccess, McpServer},
+        output::{rows_to_table, OutputFormat},
+        repl::Repl,
+        server,
+    },
+};
+
+#[derive(Debug, Parser)]
+#[command(name = "all_new_db_talks", version, about = "Ask your database questions in plain language")]
+pub struct Cli {
+    #[command(flatten)]
+    pub options: GlobalOptions,
+
+    /// Without a subcommand the interactive REPL starts
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Args)]
+pub struct GlobalOptions {
+    /// TOML config file (default: TALK_DB_CONFIG, then ./talk_with_db.toml when present)
+    #[arg(long, global = true)]
+    pub config: Option<String>,
+
+    /// Database to use instead of [database] url (mysql or mariadb); built from the matching *_USERNAME, *_HOST ... env values
+    #[arg(long, value_enum, global = true)]
+    pub datasource: Option<DatasourceKind>,
+
+    /// Named datasource ([datasources.<name>]) to use; chosen from the question when omitted
+    #[arg(long, global = true)]
+    pub source: Option<String>,
+
+    /// Model used for SQL generation (overrides [models] / SQL_OPERATE)
+    #[arg(long, global = true)]
+    pub model: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = FormatArg::Text, global = true)]
+    pub format: FormatArg,
+
+    /// Keep at most this many result rows
+    #[arg(long, global = true)]
+    pub limit: Option<usize>,
+
+    /// Generate SQL but never execute it
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// Show generated SQL and ask to run, edit or cancel before executing
+    #[arg(long, global = true)]
+    pub confirm: bool,
+
+    /// Print SQL and summary tokens as the model writes them (ask only)
+    #[arg(long, global = true)]
+    pub stream: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Answer one question and exit
+    Ask { question: String },
+    /// Print tables and columns
+    Schema,
+    /// Execute raw SQL
+    Sql { sql: String },
+    /// Answer a question about a CSV file
+    Csv {
+        file: String,
+        question: String,
+        #[command(flatten)]
+        options: CsvOptions,
+    },
+    /// Run an evaluation suite (JSON array or JSON lines of {question, expected_sql?, expected_rows?})
+    Eval { suite: String },
+    /// Start the HTTP server
+    Serve {
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        addr: String,
+    },
+    /// Manage api keys for the HTTP server
+    Keys {
+        #[command(subcommand)]
+        action: KeysAction,
+    },
+    /// Expose the database as MCP tools over stdio, or over HTTP with --http
+    Mcp {
+        /// Listen on this address (POST /mcp) instead of stdio; requests need an api key, as for serve
+        #[arg(long)]
+        http: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum KeysAction {
+    /// Create a key; it is printed once and only its hash is stored
+    Add {
+        name: String,
+        /// Datasource the key may use, repeatable; all when omitted
+        #[arg(long = "datasource-name")]
+        datasources: Vec<String>,
+        /// Table the key may read, repeatable; all when omitted
+        #[arg(long = "table")]
+        tables: Vec<String>,
+        #[arg(long)]
+        allow_write: bool,
+    },
+    List,
+    Revoke { name: String },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum FormatArg {
+    Text,
+    Table,
+    Json,
+}
+
+impl From<FormatArg> for OutputFormat {
+    fn from(value: FormatArg) -> Self {
+        match value {
+            FormatArg::Text => OutputFormat::Text,
+            FormatArg::Table => OutputFormat::Table,
+            FormatArg::Json => OutputFormat::Json,
+        }
+    }
+}
+
+impl Cli {
+    /// File and environment layers, then the flags on top
+    pub fn load_config(&self) -> Result<AppConfig, Error> {
+        let mut config = AppConfig::load_with_driver(self.options.config.as_deref(), self.options.datasource)?;
+        if let Some(model) = &self.options.model {
+            if let Some(role) = config.models.roles.get_mut(&ModelSelect::SqlOperate) {
+                role.primary = model.clone();
+            }
+        }
+        if let Some(limit) = self.options.limit {
+            config.chain.row_limit = Some(limit);
+        }
+        Ok(config)
+    }
+
+    pub async fn run(self) -> Result<(), Error> {
+        let format: OutputFormat = self.options.format.into();
+        let dry_run = self.options.dry_run;
+        let config = self.load_config()?;
+
+        if let Some(Command::Keys { action }) = self.command {
+            return manage_keys(action, &config);
+        }
+        if let Some(Command::Csv { file, question, options }) = self.command {
+            return ask_csv_file(&config, &file, question, options, format, dry_run).await;
+        }
+
+        config.validate()?;
+        let datasources = DatasourceRouter::connect(&config).await?;
+        let source = self.options.source.as_deref();
+
+        match self.command {
+            None => {
+                let chain = datasources.into_chain(source)?;
+                let mut repl = Repl::new(chain, &config);
+                repl.format = format;
+                repl.confirm = self.options.confirm;
+                repl.run().await
+            }
+            Some(Command::Ask { question }) => {
+                let picked = datasources.pick(&question, source, &|_| true)?;
+                if datasources.sources.len() > 1 {
+                    eprintln!("Using datasource '{}'", picked.name);
+                }
+                let chain = picked.chain.as_ref();
+                if dry_run {
+                    let generated = chain.generate_sql(question).await?;
+                    match format {
+                        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&generated)?),
+                        OutputFormat::Text | OutputFormat::Table => print_generated(&generated),
+                    }
+                    return Ok(());
+                }
+                if self.options.confirm {
+                    let generated = chain.generate_sql(question).await?;
+                    let mut editor = DefaultEditor::new()?;
+                    return match confirm_sql(chain, &mut editor, generated).await? {
+                        ConfirmDecision::Run(generated) => {
+                            let response = ChainResponse::Answer(chain.answer_generated(&generated).await?);
+                            println!("{}", format.render(&response));
+                            Ok(())
+                        }
+                        ConfirmDecision::Cancel => {
+                            println!("Cancelled, nothing was executed");
+                            Ok(())
+                        }
+                    };
+                }
+                if self.options.stream {
+                    return stream_to_stdout(chain, question, format).await;
+                }
+                let response = chain.respond(question).await?;
+                println!("{}", format.render(&response));
+                Ok(())
+            }
+            Some(Command::Schema) => {
+                let chain = &datasources.named(source)?.chain;
+                let response = ChainResponse::Schema(chain.get_db_info().await?);
+                println!("{}", format.render(&response));
+                Ok(())
+            }
+            Some(Command::Sql { sql }) => {
+                let chain = &datasources.pick(&sql, source, &|_| true)?.chain;
+                let generated = chain.validate_sql(String::new(), sql).await;
+                if dry_run {
+                    print_generated(&generated);
+                    return Ok(());
+                }
+                let rows = chain.execute_generated(&generated).await?;
+                match format {
+                    OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&rows)?),
+                    OutputFormat::Text | OutputFormat::Table => println!("{}", rows_to_table(&rows)),
+                }
+                Ok(())
+            }
+            Some(Command::Csv { .. }) => unreachable!("handled before connecting"),
+            Some(Command::Eval { suite }) => {
+                let cases = load_suite(&suite)?;
+                let chain = &datasources.named(source)?.chain;
+                let report = run_suite(chain, &cases, dry_run).await;
+                match format {
+                    OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+                    OutputFormat::Text | OutputFormat::Table => {
+                        for outcome in &report.outcomes {
+                            let mark = if outcome.passed { "PASS" } else { "FAIL" };
+                            println!("[{}] {} ({} ms)", mark, outcome.question, outcome.duration_ms);
+                            if let Some(sql) = &outcome.generated_sql {
+                                println!("       {}", sql);
+                            }
+                            println!("       {}", outcome.detail);
+                        }
+                        println!(
+                            "{}/{} passed ({:.1}%)",
+                            report.passed,
+                            report.total,
+                            report.accuracy() * 100.0
+                        );
+                    }
+                }
+                if report.passed < report.total {
+                    return Err(anyhow!("{} of {} cases failed", report.total - report.passed, report.total));
+                }
+                Ok(())
+            }
+            Some(Command::Serve { addr }) => server::serve(datasources, &config, &addr).await,
+            Some(Command::Keys { .. }) => unreachable!("handled before connecting"),
+            Some(Command::Mcp { http }) => {
+                let server = McpServer::new(datasources);
+                match http {
+                    Some(addr) => {
+                        let limits = &config.limits;
+                        let access = McpAccess {
+                            auth: Arc::new(server::authenticator(&config)?),
+                            rate: Arc::new(RateLimiter::new(limits.burst, limits.requests_per_minute)),
+                        };
+                        mcp::serve_http(server.with_access(access), &addr).await
+                    }
+                    None => mcp::serve_stdio(server).await,
+                }
+            }
+        }
+    }
+}
+
+/// A csv question only needs the file and the models, not the database
+async fn ask_csv_file(config: &AppConfig, file: &str, question: String, options: CsvOptions, format: OutputFormat, dry_run: bool) -> Result<(), Error> {
+    config.validate_models()?;
+    let chain = TextToSqlChain::connect_csv(config, file, options).await?;
+    if dry_run {
+        println!("{}", chain.generate_csv_sql(question, &unreported).await?);
+        return Ok(());
+    }
+    let response = chain.ask_csv(question).await?;
+    println!("{}", format.render(&response));
+    Ok(())
+}
+
+/// Key management only needs the local SQLite store, not the database or the models
+fn manage_keys(action: KeysAction, config: &AppConfig) -> Result<(), Error> {
+    let mut store = ApiKeyStore::open(&config.session.db_path)?;
+    match action {
+        KeysAction::Add { name, datasources, tables, allow_write } => {
+            let key = generate_api_key();
+            let mut policy = ApiKeyPolicy::new(&name, &key);
+            if !datasources.is_empty() {
+                policy.datasources = datasources;
+            }
+            if !tables.is_empty() {
+                policy.tables = tables;
+            }
+            policy.allow_write = allow_write;
+            store.add(&policy)?;
+            println!("Created key '{}'. Store it now, it cannot be shown again:\n{}", name, key);
+        }
+        KeysAction::List => {
+            for policy in store.list()? {
+                println!(
+                    "{}  datasources: {}  tables: {}  write: {}",
+                    policy.name,
+                    policy.datasources.join(","),
+                    policy.tables.join(","),
+                    policy.allow_write
+                );
+            }
+        }
+        KeysAction::Revoke { name } => {
+            if !store.revoke(&name)? {
+                return Err(anyhow!("no api key named '{}'", name));
+            }
+            println!("Revoked key '{}'", name);
+        }
+    }
+    Ok(())
+}
+
+/// Run a question through the streaming pipeline, printing progress as it arrives.
+/// With json output every event is printed as one json line.
+async fn stream_to_stdout(chain: &TextToSqlChain, question: String, format: OutputFormat) -> Result<(), Error> {
+    let (events, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    let printer = async {
+        let mut failure = None;
+        let mut stdout = std::io::stdout();
+        while let Some(event) = receiver.recv().await {
+            if let OutputFormat::Json = format {
+                println!("{}", serde_json::to_string(&event)?);
+            }
+            match event {
+                ChainEvent::Error(message) => failure = Some(message),
+                _ if matches!(format, OutputFormat::Json) => {}
+                ChainEvent::SqlToken(token) | ChainEvent::SummaryToken(token) => print!("{}", token),
+                ChainEvent::Validated(generated) => {
+                    println!();
+                    for finding in &generated.findings {
+                        println!("  {}", finding);
+                    }
+                }
+                ChainEvent::Queued { position, .. } => println!("Waiting in line ({})...", position),
+                ChainEvent::Executing => println!("Executing..."),
+                ChainEvent::Columns(_) => {}
+                ChainEvent::Rows(rows) => println!("{} rows received", rows.len()),
+                ChainEvent::Done { row_count, .. } => println!("\n({} rows)", row_count),
+            }
+            stdout.flush()?;
+        }
+        Ok::<_, Error>(failure)
+    };
+
+    let (_, failure) = tokio::join!(stream_ask(chain, question, "", &|_| Ok(()), events), printer);
+    match failure? {
+        Some(message) => Err(anyhow!(message)),
+        None => Ok(()),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use clap::Parser;
+
+    use super::{Cli, Command};
+
+    #[test]
+    fn test_parse_ask_with_flags() {
+        let cli = Cli::parse_from(["all_new_db_talks", "ask", "how many albums?", "--dry-run", "--limit", "5"]);
+        assert!(cli.options.dry_run);
+        assert_eq!(cli.options.limit, Some(5));
+        assert!(matches!(cli.command, Some(Command::Ask { question }) if question == "how many albums?"));
+    }
+}
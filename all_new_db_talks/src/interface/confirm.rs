@@ -0,0 +1,54 @@
+use anyhow::Error;
+use rustyline::{error::ReadlineError, DefaultEditor};
+
+use crate::agent::{sql_validation::GeneratedSql, text_to_sql::TextToSqlChain};
+
+pub enum ConfirmDecision {
+    Run(GeneratedSql),
+    Cancel,
+}
+
+/// Print the generated sql with its findings and let the user run, edit or cancel it.
+/// Edited sql is validated again; sql with errors cannot be run.
+pub async fn confirm_sql(
+    chain: &TextToSqlChain,
+    editor: &mut DefaultEditor,
+    generated: GeneratedSql,
+) -> Result<ConfirmDecision, Error> {
+    let mut current = generated;
+    loop {
+        print_generated(&current);
+
+        let answer = match editor.readline("[r]un, [e]dit, [c]ancel? ") {
+            Ok(answer) => answer,
+            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => return Ok(ConfirmDecision::Cancel),
+            Err(err) => return Err(err.into()),
+        };
+
+        match answer.trim().to_lowercase().as_str() {
+            "r" | "run" | "y" | "yes" => {
+                if current.is_executable() {
+                    return Ok(ConfirmDecision::Run(current));
+                }
+                println!("This SQL has errors and cannot be run, edit or cancel it.");
+            }
+            "e" | "edit" => {
+                let edited = match editor.readline_with_initial("sql> ", (current.sql.as_str(), "")) {
+                    Ok(edited) => edited,
+                    Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => continue,
+                    Err(err) => return Err(err.into()),
+                };
+                current = chain.validate_sql(current.question.clone(), edited.trim().to_string()).await;
+            }
+            "c" | "cancel" | "n" | "no" => return Ok(ConfirmDecision::Cancel),
+            _ => println!("Please answer r, e or c."),
+        }
+    }
+}
+
+pub fn print_generated(generated: &GeneratedSql) {
+    println!("SQL: {}", generated.sql);
+    for finding in &generated.findings {
+        println!("  {}", finding);
+    }
+}
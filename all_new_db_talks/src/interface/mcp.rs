@@ -0,0 +1,370 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Error};
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::post,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+use crate::{
+    agent::{
+        access_policy::{ApiKeyPolicy, Authenticator},
+        datasource_router::{Datasource, DatasourceRouter},
+        limits::RateLimiter,
+        text_to_sql::TextToSqlChain,
+    },
+    error::TalkError,
+    interface::server::authorize_key,
+};
+
+pub const PROTOCOL_VERSION: &str = "2025-03-26";
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+#[derive(Debug, Deserialize)]
+pub struct JsonRpcRequest {
+    #[serde(default)]
+    pub jsonrpc: String,
+    /// Absent for notifications, which get no response
+    #[serde(default)]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JsonRpcResponse {
+    pub jsonrpc: &'static str,
+    pub id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<JsonRpcError>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JsonRpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl JsonRpcResponse {
+    fn success(id: Value, result: Value) -> Self {
+        Self { jsonrpc: "2.0", id, result: Some(result), error: None }
+    }
+
+    fn failure(id: Value, code: i64, message: impl Into<String>) -> Self {
+        Self {
+            jsonrpc: "2.0",
+            id,
+            result: None,
+            error: Some(JsonRpcError { code, message: message.into() }),
+        }
+    }
+}
+
+/// Tools advertised by `tools/list`. Every tool but `list_datasources` takes an optional `datasource`.
+pub fn tool_definitions() -> Value {
+    let datasource = json!({
+        "type": "string",
+        "description": "Datasource name from list_datasources; the default, or the one the input is about, when omitted"
+    });
+    json!([
+        {
+            "name": "list_datasources",
+            "description": "List the configured datasources",
+            "inputSchema": { "type": "object", "properties": {} }
+        },
+        {
+            "name": "list_tables",
+            "description": "List the tables of the database",
+            "inputSchema": { "type": "object", "properties": { "datasource": datasource } }
+        },
+        {
+            "name": "describe_table",
+            "description": "List the columns of one table",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "table": { "type": "string" }, "datasource": datasource },
+                "required": ["table"]
+            }
+        },
+        {
+            "name": "run_readonly_query",
+            "description": "Run a single read-only SQL statement; writes and multiple statements are refused",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "sql": { "type": "string" }, "datasource": datasource },
+                "required": ["sql"]
+            }
+        },
+        {
+            "name": "ask_question",
+            "description": "Answer a question in plain language: generates, checks and runs SQL, then summarizes the result",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "question": { "type": "string" }, "datasource": datasource },
+                "required": ["question"]
+            }
+        }
+    ])
+}
+
+/// Api keys and rate limits of the http transport, the same as the http server's
+#[derive(Clone)]
+pub struct McpAccess {
+    pub auth: Arc<Authenticator>,
+    pub rate: Arc<RateLimiter>,
+}
+
+/// Exposes the datasources as MCP tools. Every query goes through the same
+/// validation as the rest of the crate, so only read-only sql is executed.
+#[derive(Clone)]
+pub struct McpServer {
+    pub datasources: Arc<DatasourceRouter>,
+    /// Checked for every http message; stdio serves the local user and has none
+    pub access: Option<McpAccess>,
+}
+
+impl McpServer {
+    pub fn new(datasources: DatasourceRouter) -> Self {
+        Self { datasources: Arc::new(datasources), access: None }
+    }
+
+    pub fn with_access(mut self, access: McpAccess) -> Self {
+        self.access = Some(access);
+        self
+    }
+
+    /// Handle one JSON-RPC message; notifications return `None`.
+    /// `policy` limits the datasources and tables the tools may touch.
+    pub async fn handle(&self, request: JsonRpcRequest, policy: Option<&ApiKeyPolicy>) -> Option<JsonRpcResponse> {
+        let id = request.id?;
+        if request.jsonrpc != "2.0" {
+            return Some(JsonRpcResponse::failure(id, INVALID_REQUEST, "jsonrpc must be \"2.0\""));
+        }
+
+        let response = match request.method.as_str() {
+            "initialize" => JsonRpcResponse::success(
+                id,
+                json!({
+                    "protocolVersion": PROTOCOL_VERSION,
+                    "capabilities": { "tools": {} },
+                    "serverInfo": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") }
+                }),
+            ),
+            "ping" => JsonRpcResponse::success(id, json!({})),
+            "tools/list" => JsonRpcResponse::success(id, json!({ "tools": tool_definitions() })),
+            "tools/call" => match self.call_tool(&request.params, policy).await {
+                Ok(result) => JsonRpcResponse::success(id, result),
+                Err(err) => JsonRpcResponse::failure(id, INVALID_PARAMS, err.to_string()),
+            },
+            other => JsonRpcResponse::failure(id, METHOD_NOT_FOUND, format!("unknown method '{}'", other)),
+        };
+        Some(response)
+    }
+
+    /// Parse and handle one raw message, answering parse errors as JSON-RPC errors
+    pub async fn handle_message(&self, message: &str, policy: Option<&ApiKeyPolicy>) -> Option<JsonRpcResponse> {
+        match serde_json::from_str::<JsonRpcRequest>(message) {
+            Ok(request) => self.handle(request, policy).await,
+            Err(err) => Some(JsonRpcResponse::failure(Value::Null, PARSE_ERROR, err.to_string())),
+        }
+    }
+
+    /// Unknown tools and missing arguments are protocol errors;
+    /// failures while running a tool are reported in the result with `isError`.
+    async fn call_tool(&self, params: &Value, policy: Option<&ApiKeyPolicy>) -> Result<Value, Error> {
+        let name = params["name"].as_str().ok_or_else(|| anyhow!("missing tool name"))?;
+        let arguments = &params["arguments"];
+        let string_arg = |key: &str| {
+            arguments[key]
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| anyhow!("tool '{}' needs a string argument '{}'", name, key))
+        };
+
+        let requested = arguments["datasource"].as_str();
+        let may_use = |name: &str| policy.is_none_or(|policy| policy.check_datasource(name).is_ok());
+
+        let outcome = match name {
+            "list_datasources" => {
+                let visible: Vec<_> = self.datasources.list().into_iter().filter(|info| may_use(&info.name)).collect();
+                Ok(json!({ "datasources": visible }))
+            }
+            "list_tables" => match self.allowed(self.datasources.named(requested), policy) {
+                Ok(source) => self.list_tables(&source.chain, policy).await,
+                Err(err) => Err(err),
+            },
+            "describe_table" => {
+                let table = string_arg("table")?;
+                match self.allowed(self.datasources.named(requested), policy) {
+                    Ok(source) => self.describe_table(&source.chain, &table, policy).await,
+                    Err(err) => Err(err),
+                }
+            }
+            "run_readonly_query" => {
+                let sql = string_arg("sql")?;
+                match self.allowed(self.datasources.pick(&sql, requested, &may_use), policy) {
+                    Ok(source) => self.run_readonly_query(&source.chain, sql, policy).await,
+                    Err(err) => Err(err),
+                }
+            }
+            "ask_question" => {
+                let question = string_arg("question")?;
+                match self.allowed(self.datasources.pick(&question, requested, &may_use), policy) {
+                    Ok(source) => self.ask_question(&source.chain, question, policy).await,
+                    Err(err) => Err(err),
+                }
+            }
+            other => return Err(anyhow!("unknown tool '{}'", other)),
+        };
+
+        Ok(match outcome {
+            Ok(value) => tool_result(serde_json::to_string_pretty(&value)?, false),
+            Err(err) => tool_result(err.to_string(), true),
+        })
+    }
+
+    /// A named datasource still has to be one the key may use
+    fn allowed<'a>(&self, source: Result<&'a Datasource, Error>, policy: Option<&ApiKeyPolicy>) -> Result<&'a Datasource, Error> {
+        let source = source?;
+        if let Some(policy) = policy {
+            policy.check_datasource(&source.name).map_err(TalkError::from)?;
+        }
+        Ok(source)
+    }
+
+    /// A csv datasource is one table, described from its header
+    async fn list_tables(&self, chain: &TextToSqlChain, policy: Option<&ApiKeyPolicy>) -> Result<Value, Error> {
+        let tables: Vec<String> = if chain.is_csv_only() {
+            chain.get_db_info().await?.schemas.into_iter().map(|table| table.table_name).collect()
+        } else {
+            chain.pool()?.run(|db| async move { db.table_names().await }).await?
+        };
+        let visible: Vec<String> = tables.into_iter().filter(|table| policy.is_none_or(|policy| policy.may_read(table))).collect();
+        Ok(json!({ "tables": visible }))
+    }
+
+    /// Tables the key may not read are reported as missing
+    async fn describe_table(&self, chain: &TextToSqlChain, table: &str, policy: Option<&ApiKeyPolicy>) -> Result<Value, Error> {
+        if policy.is_some_and(|policy| !policy.may_read(table)) {
+            return Err(anyhow!("table '{}' does not exist", table));
+        }
+        let schema = if chain.is_csv_only() {
+            let schemas = chain.get_db_info().await?.schemas;
+            schemas.into_iter().find(|schema| schema.table_name.eq_ignore_ascii_case(table))
+        } else {
+            chain.pool()?.run(|db| async move { db.describe_table(table).await }).await?
+        };
+        match schema {
+            Some(schema) => Ok(serde_json::to_value(schema)?),
+            None => Err(anyhow!("table '{}' does not exist", table)),
+        }
+    }
+
+    async fn run_readonly_query(&self, chain: &TextToSqlChain, sql: String, policy: Option<&ApiKeyPolicy>) -> Result<Value, Error> {
+        let generated = chain.validate_sql(String::new(), sql).await;
+        if let Some(policy) = policy {
+            policy.check_sql(&generated.sql).map_err(TalkError::from)?;
+        }
+        let result = chain.execute_typed(&generated).await?;
+        Ok(json!({ "sql": generated.sql, "findings": generated.findings, "result": result }))
+    }
+
+    async fn ask_question(&self, chain: &TextToSqlChain, question: String, policy: Option<&ApiKeyPolicy>) -> Result<Value, Error> {
+        let generated = chain.generate_sql(question).await?;
+        if let Some(policy) = policy {
+            policy.check_sql(&generated.sql).map_err(TalkError::from)?;
+        }
+        let answer = chain.answer_generated(&generated).await?;
+        Ok(json!({
+            "question": answer.question,
+            "sql": answer.sql,
+            "findings": generated.findings,
+            "summary": answer.narrative,
+            "rows": answer.data,
+        }))
+    }
+}
+
+fn tool_result(text: String, is_error: bool) -> Value {
+    json!({ "content": [{ "type": "text", "text": text }], "isError": is_error })
+}
+
+/// Newline-delimited JSON-RPC over stdin/stdout. Logs go to stderr so stdout stays clean.
+pub async fn serve_stdio(server: McpServer) -> Result<(), Error> {
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = server.handle_message(&line, None).await {
+            let mut body = serde_json::to_vec(&response)?;
+            body.push(b'\n');
+            stdout.write_all(&body).await?;
+            stdout.flush().await?;
+        }
+    }
+    Ok(())
+}
+
+pub fn router(server: McpServer) -> Router {
+    Router::new().route("/mcp", post(http_message)).with_state(server)
+}
+
+/// JSON-RPC over HTTP: one message per POST, notifications are acknowledged with 202.
+/// With `access` set every message needs an api key, as on the http server.
+pub async fn serve_http(server: McpServer, addr: &str) -> Result<(), Error> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    eprintln!("MCP endpoint on http://{}/mcp", listener.local_addr()?);
+    axum::serve(listener, router(server)).await?;
+    Ok(())
+}
+
+async fn http_message(State(server): State<McpServer>, headers: HeaderMap, body: String) -> impl IntoResponse {
+    let policy = match &server.access {
+        Some(access) => match authorize_key(&access.auth, &access.rate, &headers).await {
+            Ok(policy) => policy,
+            Err(err) => return err.into_response(),
+        },
+        None => None,
+    };
+    match server.handle_message(&body, policy.as_ref()).await {
+        Some(response) => (StatusCode::OK, Json(response)).into_response(),
+        None => StatusCode::ACCEPTED.into_response(),
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{tool_definitions, JsonRpcRequest};
+
+    #[test]
+    fn test_tools_and_notifications() {
+        let names: Vec<String> = tool_definitions()
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(names, ["list_datasources", "list_tables", "describe_table", "run_readonly_query", "ask_question"]);
+
+        let notification: JsonRpcRequest =
+            serde_json::from_str(r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#).unwrap();
+        assert!(notification.id.is_none());
+    }
+}
@@ -0,0 +1,6 @@
+pub mod repl;
+pub mod output;
+pub mod cli;
+pub mod confirm;
+pub mod server;
+pub mod mcp;
\ No newline at end of file
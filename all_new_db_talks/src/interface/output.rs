@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use crate::agent::text_to_sql::ChainResponse;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Table,
+    Json,
+}
+
+impl OutputFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "text" => Some(OutputFormat::Text),
+            "table" => Some(OutputFormat::Table),
+            "json" => Some(OutputFormat::Json),
+            _ => None,
+        }
+    }
+
+    pub fn render(&self, response: &ChainResponse) -> String {
+        match self {
+            OutputFormat::Text => response.to_string(),
+            OutputFormat::Json => serde_json::to_string_pretty(response)
+                .unwrap_or_else(|err| format!("{{\"error\": \"{}\"}}", err)),
+            OutputFormat::Table => match response {
+                ChainResponse::Answer(answer) => {
+                    format!("{}\n\n{}", response, rows_to_table(&answer.data))
+                }
+                _ => response.to_string(),
+            },
+        }
+    }
+}
+
+/// Render every row as a pipe separated table with sorted column names
+pub fn rows_to_table(data: &[HashMap<String, String>]) -> String {
+    let Some(first) = data.first() else {
+        return "(no rows)".to_string();
+    };
+    let mut headers: Vec<&String> = first.keys().collect();
+    headers.sort();
+
+    let mut output = String::new();
+    output.push_str(&headers.iter().map(|h| h.as_str()).collect::<Vec<_>>().join(" | "));
+    output.push('\n');
+    output.push_str(&headers.iter().map(|_| "----").collect::<Vec<_>>().join(" | "));
+    output.push('\n');
+    for row in data {
+        let cells: Vec<&str> = headers
+            .iter()
+            .map(|h| row.get(*h).map(String::as_str).unwrap_or("NULL"))
+            .collect();
+        output.push_str(&cells.join(" | "));
+        output.push('\n');
+    }
+    output
+}
+
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::{rows_to_table, OutputFormat};
+
+    #[test]
+    fn test_rows_to_table() {
+        let data = vec![HashMap::from([
+            ("name".to_string(), "AC/DC".to_string()),
+            ("albums".to_string(), "2".to_string()),
+        ])];
+        let table = rows_to_table(&data);
+        assert_eq!(table.lines().next(), Some("albums | name"));
+        assert!(table.contains("2 | AC/DC"));
+    }
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(OutputFormat::parse("JSON"), Some(OutputFormat::Json));
+        assert_eq!(OutputFormat::parse("xml"), None);
+    }
+}
@@ -0,0 +1,274 @@
+use anyhow::{anyhow, Error};
+use rustyline::{error::ReadlineError, DefaultEditor};
+
+use crate::{
+    agent::{
+        model_router::unreported,
+        session::ConversationSession,
+        text_to_sql::{ChainResponse, TextToSqlChain},
+    },
+    configuration::{app_config::AppConfig, csv_config::CsvOptions, repl_config::ReplConfig},
+    datasource::{csv_utill::CsvUtill, session_store::SessionStore},
+    interface::{
+        confirm::{confirm_sql, ConfirmDecision},
+        output::{rows_to_table, OutputFormat},
+    },
+};
+
+const HELP: &str = "Meta-commands:
+  :schema             show tables and columns
+  :tables             list table names
+  :sql                show the last generated SQL
+  :explain            run EXPLAIN on the last generated SQL
+  :format <fmt>       output format: text | table | json
+  :confirm on|off     review generated SQL before it is executed
+  :source csv <path> [table]
+                      answer csv questions from <path>, table named after the file by default
+  :source db          stop using the csv file
+  :help               show this help
+  :quit               exit
+End a line with \\ to continue the question on the next line.";
+
+enum MetaOutcome {
+    Continue,
+    Quit,
+}
+
+pub struct Repl {
+    pub chain: TextToSqlChain,
+    pub session: ConversationSession,
+    pub store: Option<SessionStore>,
+    pub format: OutputFormat,
+    /// Review every generated statement before execution
+    pub confirm: bool,
+    config: ReplConfig,
+}
+
+impl Repl {
+    pub fn new(chain: TextToSqlChain, config: &AppConfig) -> Self {
+        let mut store = match SessionStore::open(&config.session.db_path) {
+            Ok(store) => Some(store),
+            Err(err) => {
+                eprintln!("Session store unavailable, history will not be saved: {}", err);
+                None
+            }
+        };
+        // [repl] session_id resumes a stored conversation
+        let session = match (config.repl.session_id.as_deref(), store.as_mut()) {
+            (Some(id), Some(store)) => store.load(id).ok().flatten().unwrap_or_else(|| {
+                eprintln!("Session '{}' not found, starting a new one", id);
+                ConversationSession::new()
+            }),
+            _ => ConversationSession::new(),
+        };
+
+        Self {
+            chain,
+            session,
+            store,
+            format: OutputFormat::Text,
+            confirm: false,
+            config: config.repl.clone(),
+        }
+    }
+
+    pub async fn run(&mut self) -> Result<(), Error> {
+        let mut editor = DefaultEditor::new()?;
+        // a missing history file is normal on first start
+        let _ = editor.load_history(&self.config.history_path);
+
+        println!("Session {} — ask a question, or :help for commands", self.session.id);
+        loop {
+            let Some(input) = self.read_input(&mut editor)? else {
+                break;
+            };
+            let _ = editor.add_history_entry(input.as_str());
+
+            if input.starts_with(':') {
+                match self.handle_meta(&input).await {
+                    Ok(MetaOutcome::Quit) => break,
+                    Ok(MetaOutcome::Continue) => {}
+                    Err(err) => eprintln!("Error: {}", err),
+                }
+                continue;
+            }
+
+            self.ask(&mut editor, input).await;
+        }
+
+        if let Err(err) = editor.save_history(&self.config.history_path) {
+            eprintln!("Failed to save history: {}", err);
+        }
+        Ok(())
+    }
+
+    /// Read one (possibly multi-line) input. `None` on EOF.
+    fn read_input(&self, editor: &mut DefaultEditor) -> Result<Option<String>, Error> {
+        let mut buffer = String::new();
+        loop {
+            let prompt = if buffer.is_empty() { "talk> " } else { "  ... " };
+            match editor.readline(prompt) {
+                Ok(line) => {
+                    if let Some(stripped) = line.strip_suffix('\\') {
+                        buffer.push_str(stripped);
+                        buffer.push('\n');
+                        continue;
+                    }
+                    buffer.push_str(&line);
+                    let input = buffer.trim().to_string();
+                    if input.is_empty() {
+                        buffer.clear();
+                        continue;
+                    }
+                    return Ok(Some(input));
+                }
+                // Ctrl-C drops the current input, Ctrl-D quits
+                Err(ReadlineError::Interrupted) => {
+                    buffer.clear();
+                    continue;
+                }
+                Err(ReadlineError::Eof) => return Ok(None),
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    async fn ask(&mut self, editor: &mut DefaultEditor, input: String) {
+        if self.confirm {
+            self.ask_with_confirm(editor, input).await;
+            return;
+        }
+
+        let response = match self.chain.respond_in_session(&mut self.session, input).await {
+            Ok(response) => response,
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                return;
+            }
+        };
+        println!("{}", self.format.render(&response));
+
+        if let ChainResponse::Clarification(request) = &response {
+            let choice = match self.read_input(editor) {
+                Ok(Some(choice)) => choice,
+                _ => return,
+            };
+            match self.chain.resolve_clarification_in_session(&mut self.session, request, &choice).await {
+                Ok(resolved) => println!("{}", self.format.render(&resolved)),
+                Err(err) => eprintln!("Error: {}", err),
+            }
+        }
+
+        self.save_session();
+    }
+
+    /// Straight to sql generation, nothing runs until the user confirms
+    async fn ask_with_confirm(&mut self, editor: &mut DefaultEditor, input: String) {
+        let generated = match self.chain.generate_sql_in_session(&self.session, input, &unreported).await {
+            Ok(generated) => generated,
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                return;
+            }
+        };
+        let generated = match confirm_sql(&self.chain, editor, generated).await {
+            Ok(ConfirmDecision::Run(generated)) => generated,
+            Ok(ConfirmDecision::Cancel) => {
+                println!("Cancelled, nothing was executed");
+                return;
+            }
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                return;
+            }
+        };
+        match self.chain.answer_generated_in_session(&mut self.session, &generated).await {
+            Ok(response) => println!("{}", self.format.render(&response)),
+            Err(err) => eprintln!("Error: {}", err),
+        }
+        self.save_session();
+    }
+
+    fn save_session(&mut self) {
+        if let Some(store) = self.store.as_mut() {
+            if let Err(err) = store.save(&self.session) {
+                eprintln!("Failed to save session: {}", err);
+            }
+        }
+    }
+
+    async fn handle_meta(&mut self, input: &str) -> Result<MetaOutcome, Error> {
+        let mut parts = input.split_whitespace();
+        let command = parts.next().unwrap_or_default();
+        let args: Vec<&str> = parts.collect();
+
+        match command {
+            ":quit" | ":q" | ":exit" => return Ok(MetaOutcome::Quit),
+            ":help" | ":h" => println!("{}", HELP),
+            ":schema" => println!("{}", self.chain.get_db_info().await?),
+            ":tables" => {
+                let schema = self.chain.get_db_info().await?;
+                for table in &schema.schemas {
+                    println!("{}", table.table_name);
+                }
+            }
+            ":sql" => match self.session.last_sql() {
+                Some(sql) => println!("{}", sql),
+                None => println!("No SQL has been generated yet"),
+            },
+            ":explain" => {
+                let sql = self
+                    .session
+                    .last_sql()
+                    .ok_or_else(|| anyhow!("No SQL has been generated yet"))?;
+                let explain = format!("EXPLAIN {}", sql);
+                let plan = self
+                    .chain
+                    .pool()?
+                    .run(|db| {
+                        let explain = explain.as_str();
+                        async move { db.query(explain, None).await }
+                    })
+                    .await
+                    .map_err(|err| anyhow!("explain failed: {}", err))?;
+                println!("{}", rows_to_table(&plan));
+            }
+            ":format" => {
+                let format = args
+                    .first()
+                    .and_then(|value| OutputFormat::parse(value))
+                    .ok_or_else(|| anyhow!("usage: :format text|table|json"))?;
+                self.format = format;
+                println!("Output format set to {:?}", format);
+            }
+            ":confirm" => {
+                self.confirm = match args.first().copied() {
+                    Some("on") => true,
+                    Some("off") => false,
+                    _ => return Err(anyhow!("usage: :confirm on|off")),
+                };
+                println!("Confirm mode {}", if self.confirm { "on" } else { "off" });
+            }
+            ":source" => match args.as_slice() {
+                ["csv", path, rest @ ..] if rest.len() <= 1 => {
+                    let options = CsvOptions { table_name: rest.first().map(|table| table.to_string()), ..Default::default() };
+                    options.validate("source")?;
+                    let csv = CsvUtill::with_options(path.to_string(), options);
+                    if !csv.verify_path() {
+                        return Err(anyhow!("csv file '{}' does not exist", path));
+                    }
+                    println!("CSV source set to {} as table {}", path, csv.table_name());
+                    self.chain.attach_csv(csv);
+                }
+                ["db"] => {
+                    self.chain.detach_csv();
+                    println!("CSV source removed, answering from the database only");
+                }
+                _ => return Err(anyhow!("usage: :source csv <path> [table] | :source db")),
+            },
+            other => return Err(anyhow!("unknown command '{}', try :help", other)),
+        }
+        Ok(MetaOutcome::Continue)
+    }
+}
+
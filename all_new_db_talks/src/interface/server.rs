@@ -0,0 +1,702 @@
+use std::{
+    convert::Infallible,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::Error;
+use axum::{
+    extract::{Path, Query, State},
+    http::{
+        header::{AUTHORIZATION, RETRY_AFTER},
+        HeaderMap, HeaderValue, StatusCode,
+    },
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::UnboundedReceiverStream, Stream, StreamExt};
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, SecurityScheme},
+    IntoParams, Modify, OpenApi, ToSchema,
+};
+
+use crate::{
+    agent::{
+        access_policy::{AccessDenied, ApiKeyPolicy, Authenticator},
+        datasource_router::{Datasource, DatasourceHealth, DatasourceInfo, DatasourceRouter},
+        limits::{ConcurrencyLimiter, LimitPermit, QueueReport, RateLimiter},
+        model_router::unreported,
+        session::ConversationSession,
+        session::ConversationTurn,
+        sql_validation::{GeneratedSql, Severity, ValidationFinding},
+        streaming::{stream_ask, ChainEvent},
+        summarizer::AnswerSummarizer,
+        text_to_sql::{ChainResponse, TextToSqlChain},
+    },
+    configuration::{app_config::AppConfig, datasource_config::DatasourceKind},
+    error::TalkError,
+    datasource::{
+        api_key_store::ApiKeyStore,
+        db_utill::{ColumnName, DatabaseSchema, TableSchema},
+        pool::PoolHealth,
+        result_set::{ColumnKind, ResultColumn, ResultSet},
+        session_store::SessionStore,
+    },
+};
+
+#[derive(Clone)]
+pub struct AppState {
+    pub datasources: Arc<DatasourceRouter>,
+    pub store: Option<Arc<Mutex<SessionStore>>>,
+    pub auth: Arc<Authenticator>,
+    /// Token bucket per api key
+    pub rate: Arc<RateLimiter>,
+    /// Bounds the requests running against each datasource
+    pub datasource_slots: Arc<ConcurrencyLimiter>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AskRequest {
+    pub question: String,
+    /// Continue an existing conversation; a new session is created when absent
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// Datasource to ask; chosen from the question when absent
+    #[serde(default)]
+    pub datasource: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AskResponse {
+    pub session_id: Option<String>,
+    /// Datasource that answered
+    pub datasource: String,
+    pub question: String,
+    pub sql: String,
+    pub findings: Vec<ValidationFinding>,
+    pub result: ResultSet,
+    pub summary: String,
+    /// Every wait for a free datasource slot or a busy model, in order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub queued: Vec<QueueReport>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ExecuteRequest {
+    pub sql: String,
+    /// Datasource to run on; chosen from the tables the sql names when absent
+    #[serde(default)]
+    pub datasource: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ExecuteResponse {
+    pub datasource: String,
+    pub sql: String,
+    pub findings: Vec<ValidationFinding>,
+    pub result: ResultSet,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queued: Option<QueueReport>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct SchemaQuery {
+    /// Datasource to describe; the default one when absent
+    pub datasource: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ErrorBody {
+    pub error: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub findings: Vec<ValidationFinding>,
+}
+
+/// Every handler error becomes a JSON body with a matching status code
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub message: String,
+    pub findings: Vec<ValidationFinding>,
+    /// Sent as the `Retry-After` header
+    pub retry_after: Option<Duration>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self { status, message: message.into(), findings: Vec::new(), retry_after: None }
+    }
+
+    pub fn rate_limited(retry_after: Duration) -> Self {
+        Self {
+            retry_after: Some(retry_after),
+            ..Self::new(StatusCode::TOO_MANY_REQUESTS, "rate limit exceeded for this api key")
+        }
+    }
+
+    pub fn rejected(generated: &GeneratedSql) -> Self {
+        Self {
+            status: StatusCode::UNPROCESSABLE_ENTITY,
+            message: "the generated SQL failed validation and was not executed".to_string(),
+            findings: generated.findings.clone(),
+            retry_after: None,
+        }
+    }
+}
+
+impl From<TalkError> for ApiError {
+    fn from(err: TalkError) -> Self {
+        let status = match &err {
+            TalkError::Config(_) | TalkError::Execution(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            TalkError::Connection(_) => StatusCode::SERVICE_UNAVAILABLE,
+            TalkError::Llm(_) | TalkError::Extraction(_) => StatusCode::BAD_GATEWAY,
+            TalkError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            TalkError::Policy(_) => StatusCode::FORBIDDEN,
+            TalkError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+        };
+        let message = err.to_string();
+        match err {
+            TalkError::Validation(findings) => Self { findings, ..Self::new(status, message) },
+            _ => Self::new(status, message),
+        }
+    }
+}
+
+impl From<Error> for ApiError {
+    fn from(err: Error) -> Self {
+        TalkError::from(err).into()
+    }
+}
+
+impl From<AccessDenied> for ApiError {
+    fn from(denied: AccessDenied) -> Self {
+        match denied {
+            AccessDenied::Unauthenticated => Self::new(
+                StatusCode::UNAUTHORIZED,
+                "missing or unknown api key, send it as 'x-api-key' or 'Authorization: Bearer <key>'",
+            ),
+            AccessDenied::Forbidden(message) => Self::new(StatusCode::FORBIDDEN, message),
+            AccessDenied::Unavailable(message) => Self::new(StatusCode::SERVICE_UNAVAILABLE, message),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody { error: self.message, findings: self.findings };
+        let mut response = (self.status, Json(body)).into_response();
+        if let Some(retry_after) = self.retry_after {
+            // whole seconds, rounded up so an immediate retry does not fail again
+            let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+            response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(seconds));
+        }
+        response
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "all_new_db_talks", description = "Ask your database questions in plain language"),
+    paths(ask, ask_stream, generate_sql, execute_sql, schema, datasources, health, session),
+    components(schemas(
+        AskRequest,
+        AskResponse,
+        ExecuteRequest,
+        ExecuteResponse,
+        ErrorBody,
+        GeneratedSql,
+        ValidationFinding,
+        Severity,
+        ResultSet,
+        ResultColumn,
+        ColumnKind,
+        DatabaseSchema,
+        TableSchema,
+        ColumnName,
+        ConversationSession,
+        ConversationTurn,
+        ChainEvent,
+        QueueReport,
+        DatasourceInfo,
+        DatasourceKind,
+        DatasourceHealth,
+        PoolHealth,
+    )),
+    modifiers(&SecurityAddon),
+    security(("api_key" = []))
+)]
+pub struct ApiDoc;
+
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme("api_key", SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("x-api-key"))));
+    }
+}
+
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/openapi.json", get(openapi))
+        .route("/ask", post(ask))
+        .route("/ask/stream", post(ask_stream))
+        .route("/sql/generate", post(generate_sql))
+        .route("/sql/execute", post(execute_sql))
+        .route("/schema", get(schema))
+        .route("/datasources", get(datasources))
+        .route("/health", get(health))
+        .route("/sessions/{id}", get(session))
+        .with_state(state)
+}
+
+pub async fn serve(datasources: DatasourceRouter, config: &AppConfig, addr: &str) -> Result<(), Error> {
+    let store = match SessionStore::open(&config.session.db_path) {
+        Ok(store) => Some(Arc::new(Mutex::new(store))),
+        Err(err) => {
+            eprintln!("Session store unavailable, sessions will not be saved: {}", err);
+            None
+        }
+    };
+    let auth = authenticator(config)?;
+    let limits = &config.limits;
+    let state = AppState {
+        datasources: Arc::new(datasources),
+        store,
+        auth: Arc::new(auth),
+        rate: Arc::new(RateLimiter::new(limits.burst, limits.requests_per_minute)),
+        datasource_slots: Arc::new(ConcurrencyLimiter::new(limits.datasource_concurrency)),
+    };
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    println!("Listening on http://{}", listener.local_addr()?);
+    axum::serve(listener, router(state)).await?;
+    Ok(())
+}
+
+/// Keys from the config and the SQLite store
+pub fn authenticator(config: &AppConfig) -> Result<Authenticator, Error> {
+    let key_store = match ApiKeyStore::open(&config.session.db_path) {
+        Ok(key_store) => Some(key_store),
+        Err(err) => {
+            eprintln!("Api key store unavailable, only keys from the config are accepted: {}", err);
+            None
+        }
+    };
+    let auth = Authenticator::new(&config.auth, key_store)?;
+    if auth.disabled {
+        eprintln!("Authentication is disabled, every request can query the database");
+    }
+    Ok(auth)
+}
+
+fn require_question(question: &str) -> Result<(), ApiError> {
+    if question.trim().is_empty() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "question must not be empty"));
+    }
+    Ok(())
+}
+
+async fn authorize(state: &AppState, headers: &HeaderMap) -> Result<Option<ApiKeyPolicy>, ApiError> {
+    authorize_key(&state.auth, &state.rate, headers).await
+}
+
+/// Resolve the caller's key, sent as `x-api-key` or `Authorization: Bearer <key>`,
+/// and take one token from its bucket. `None` means authentication is disabled.
+pub async fn authorize_key(auth: &Authenticator, rate: &RateLimiter, headers: &HeaderMap) -> Result<Option<ApiKeyPolicy>, ApiError> {
+    let presented = headers
+        .get("x-api-key")
+        .and_then(|value| value.to_str().ok())
+        .or_else(|| {
+            headers
+                .get(AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "))
+        });
+    let policy = auth.authenticate(presented).await?;
+    let bucket = policy.as_ref().map(|policy| policy.name.as_str()).unwrap_or("anonymous");
+    rate.check(bucket).map_err(ApiError::rate_limited)?;
+    Ok(policy)
+}
+
+/// The requested datasource, or the one `text` is about among those the key may use
+fn select<'a>(
+    state: &'a AppState,
+    policy: &Option<ApiKeyPolicy>,
+    requested: Option<&str>,
+    text: &str,
+) -> Result<&'a Datasource, ApiError> {
+    let allowed = |name: &str| policy.as_ref().is_none_or(|policy| policy.check_datasource(name).is_ok());
+    let source = state
+        .datasources
+        .pick(text, requested, &allowed)
+        .map_err(|err| ApiError::new(StatusCode::BAD_REQUEST, err.to_string()))?;
+    if let Some(policy) = policy {
+        policy.check_datasource(&source.name)?;
+    }
+    Ok(source)
+}
+
+/// Wait for a free slot on the datasource
+async fn datasource_slot(state: &AppState, datasource: &str) -> LimitPermit {
+    state.datasource_slots.acquire(datasource, |_| {}).await
+}
+
+fn check_sql(policy: &Option<ApiKeyPolicy>, generated: &GeneratedSql) -> Result<(), ApiError> {
+    match policy {
+        Some(policy) => Ok(policy.check_sql(&generated.sql)?),
+        None => Ok(()),
+    }
+}
+
+/// Diesel calls block, so they run on the blocking pool; a failing store answers 503
+async fn with_store<T: Send + 'static>(
+    store: &Arc<Mutex<SessionStore>>,
+    call: impl FnOnce(&mut SessionStore) -> Result<T, Error> + Send + 'static,
+) -> Result<T, ApiError> {
+    let store = store.clone();
+    let unavailable = |message: String| ApiError::new(StatusCode::SERVICE_UNAVAILABLE, message);
+    tokio::task::spawn_blocking(move || call(&mut store.lock().unwrap_or_else(|poisoned| poisoned.into_inner())))
+        .await
+        .map_err(|err| unavailable(format!("session store call did not finish: {}", err)))?
+        .map_err(|err| unavailable(format!("session store failed: {}", err)))
+}
+
+/// Load the requested session, or start a new one owned by the caller's key when no id
+/// was given. Sessions of other keys are reported as not found, so ids cannot be probed.
+async fn load_session(state: &AppState, policy: &Option<ApiKeyPolicy>, session_id: Option<&str>) -> Result<ConversationSession, ApiError> {
+    let caller = policy.as_ref().map(|policy| policy.name.as_str());
+    let not_found = |id: &str| ApiError::new(StatusCode::NOT_FOUND, format!("session '{}' not found", id));
+    match (session_id, &state.store) {
+        (None, _) => Ok(ConversationSession::owned_by(caller)),
+        (Some(_), None) => Err(ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "session store is not available")),
+        (Some(id), Some(store)) => {
+            let session_id = id.to_string();
+            let session = with_store(store, move |store| store.load(&session_id)).await?.ok_or_else(|| not_found(id))?;
+            match caller {
+                Some(caller) if session.owner.as_deref() != Some(caller) => Err(not_found(id)),
+                _ => Ok(session),
+            }
+        }
+    }
+}
+
+async fn openapi() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// Generate, validate and run sql for a question, then summarize the result
+#[utoipa::path(
+    post,
+    path = "/ask",
+    request_body = AskRequest,
+    responses(
+        (status = 200, description = "Answer with the executed sql and its rows", body = AskResponse),
+        (status = 401, description = "Missing or unknown api key", body = ErrorBody),
+        (status = 403, description = "The key may not use this datasource or these tables", body = ErrorBody),
+        (status = 429, description = "Rate limit exceeded, see Retry-After", body = ErrorBody),
+        (status = 400, description = "Empty question or unknown datasource", body = ErrorBody),
+        (status = 404, description = "Unknown session", body = ErrorBody),
+        (status = 422, description = "Generated sql failed validation", body = ErrorBody),
+        (status = 500, description = "Database failure", body = ErrorBody),
+        (status = 502, description = "No model answered, or its answer held no sql", body = ErrorBody),
+        (status = 503, description = "Database, api key store or session store unreachable", body = ErrorBody),
+        (status = 504, description = "Model or database timed out", body = ErrorBody),
+    )
+)]
+async fn ask(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<AskRequest>,
+) -> Result<Json<AskResponse>, ApiError> {
+    require_question(&request.question)?;
+    let policy = authorize(&state, &headers).await?;
+    let source = select(&state, &policy, request.datasource.as_deref(), &request.question)?;
+    let slot = datasource_slot(&state, &source.name).await;
+    let started = Instant::now();
+    let mut session = load_session(&state, &policy, request.session_id.as_deref()).await?;
+
+    let (model_waits, mut waited) = mpsc::unbounded_channel();
+    let on_model_queued = |report: &QueueReport| {
+        let _ = model_waits.send(report.clone());
+    };
+    let generated = source.chain.generate_sql_in_session(&session, request.question.clone(), &on_model_queued).await?;
+    if !generated.is_executable() {
+        return Err(ApiError::rejected(&generated));
+    }
+    check_sql(&policy, &generated)?;
+    let result = source.chain.execute_typed(&generated).await?;
+    let summarizer = AnswerSummarizer::new(source.chain.router.clone());
+    let answer = summarizer
+        .summarize(&generated.question, &generated.sql, result.to_string_rows(), &on_model_queued)
+        .await?;
+    let mut queued: Vec<QueueReport> = slot.queued.into_iter().collect();
+    while let Ok(report) = waited.try_recv() {
+        queued.push(report);
+    }
+
+    let summary = answer.narrative.clone();
+    let session_id = match &state.store {
+        Some(store) => {
+            TextToSqlChain::record_turn(&mut session, &request.question, &ChainResponse::Answer(answer), started);
+            let saved = session.clone();
+            with_store(store, move |store| store.save(&saved)).await?;
+            Some(session.id.clone())
+        }
+        None => None,
+    };
+
+    Ok(Json(AskResponse {
+        session_id,
+        datasource: source.name.clone(),
+        question: generated.question,
+        sql: generated.sql,
+        findings: generated.findings,
+        result,
+        summary,
+        queued,
+    }))
+}
+
+/// Same pipeline as `/ask`, sent as server-sent events while it runs.
+/// Each event is named after its `ChainEvent` and carries it as json.
+#[utoipa::path(
+    post,
+    path = "/ask/stream",
+    request_body = AskRequest,
+    responses(
+        (status = 200, description = "Stream of progress events", body = ChainEvent, content_type = "text/event-stream"),
+        (status = 401, description = "Missing or unknown api key", body = ErrorBody),
+        (status = 403, description = "The key may not use this datasource or these tables", body = ErrorBody),
+        (status = 429, description = "Rate limit exceeded, see Retry-After", body = ErrorBody),
+        (status = 400, description = "Empty question or unknown datasource", body = ErrorBody),
+        (status = 404, description = "Unknown session", body = ErrorBody),
+        (status = 503, description = "Api key store or session store unreachable", body = ErrorBody),
+    )
+)]
+async fn ask_stream(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<AskRequest>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    require_question(&request.question)?;
+    let policy = authorize(&state, &headers).await?;
+    let source = select(&state, &policy, request.datasource.as_deref(), &request.question)?;
+    let (datasource, chain) = (source.name.clone(), source.chain.clone());
+    let session = load_session(&state, &policy, request.session_id.as_deref()).await?;
+
+    let (events, receiver) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let _slot = state
+            .datasource_slots
+            .acquire(&datasource, |position| {
+                let _ = events.send(ChainEvent::Queued { resource: datasource.clone(), position });
+            })
+            .await;
+        let guard = move |generated: &GeneratedSql| match &policy {
+            Some(policy) => policy.check_sql(&generated.sql).map_err(|denied| ApiError::from(denied).message),
+            None => Ok(()),
+        };
+        stream_ask(&chain, request.question, &session.history_prompt(), &guard, events).await;
+    });
+
+    let stream = UnboundedReceiverStream::new(receiver).map(|event: ChainEvent| {
+        let data = serde_json::to_string(&event).unwrap_or_default();
+        Ok(Event::default().event(event.name()).data(data))
+    });
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Generate and validate sql without executing it
+#[utoipa::path(
+    post,
+    path = "/sql/generate",
+    request_body = AskRequest,
+    responses(
+        (status = 200, description = "Generated sql with validation findings", body = GeneratedSql),
+        (status = 401, description = "Missing or unknown api key", body = ErrorBody),
+        (status = 403, description = "The key may not use this datasource or these tables", body = ErrorBody),
+        (status = 429, description = "Rate limit exceeded, see Retry-After", body = ErrorBody),
+        (status = 400, description = "Empty question or unknown datasource", body = ErrorBody),
+        (status = 502, description = "No model answered, or its answer held no sql", body = ErrorBody),
+        (status = 503, description = "Api key store or session store unreachable", body = ErrorBody),
+        (status = 504, description = "Model timed out", body = ErrorBody),
+    )
+)]
+async fn generate_sql(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<AskRequest>,
+) -> Result<Json<GeneratedSql>, ApiError> {
+    require_question(&request.question)?;
+    let policy = authorize(&state, &headers).await?;
+    let source = select(&state, &policy, request.datasource.as_deref(), &request.question)?;
+    let session = load_session(&state, &policy, request.session_id.as_deref()).await?;
+    let generated = source.chain.generate_sql_in_session(&session, request.question, &unreported).await?;
+    Ok(Json(generated))
+}
+
+/// Validate and run sql, e.g. after a client edited the generated statement
+#[utoipa::path(
+    post,
+    path = "/sql/execute",
+    request_body = ExecuteRequest,
+    responses(
+        (status = 200, description = "Typed result set", body = ExecuteResponse),
+        (status = 401, description = "Missing or unknown api key", body = ErrorBody),
+        (status = 403, description = "The key may not use this datasource or these tables", body = ErrorBody),
+        (status = 429, description = "Rate limit exceeded, see Retry-After", body = ErrorBody),
+        (status = 400, description = "Empty sql or unknown datasource", body = ErrorBody),
+        (status = 422, description = "Sql failed validation", body = ErrorBody),
+        (status = 500, description = "Database failure", body = ErrorBody),
+        (status = 503, description = "Database or api key store unreachable", body = ErrorBody),
+        (status = 504, description = "Database timed out", body = ErrorBody),
+    )
+)]
+async fn execute_sql(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(request): Json<ExecuteRequest>,
+) -> Result<Json<ExecuteResponse>, ApiError> {
+    if request.sql.trim().is_empty() {
+        return Err(ApiError::new(StatusCode::BAD_REQUEST, "sql must not be empty"));
+    }
+    let policy = authorize(&state, &headers).await?;
+    let source = select(&state, &policy, request.datasource.as_deref(), &request.sql)?;
+    let generated = source.chain.validate_sql(String::new(), request.sql).await;
+    if !generated.is_executable() {
+        return Err(ApiError::rejected(&generated));
+    }
+    check_sql(&policy, &generated)?;
+    let slot = datasource_slot(&state, &source.name).await;
+    let result = source.chain.execute_typed(&generated).await?;
+    Ok(Json(ExecuteResponse {
+        datasource: source.name.clone(),
+        sql: generated.sql,
+        findings: generated.findings,
+        result,
+        queued: slot.queued,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/schema",
+    params(SchemaQuery),
+    responses(
+        (status = 400, description = "Unknown datasource, or several and none named", body = ErrorBody),
+        (status = 200, description = "Tables and their columns", body = DatabaseSchema),
+        (status = 401, description = "Missing or unknown api key", body = ErrorBody),
+        (status = 403, description = "The key may not use this datasource or these tables", body = ErrorBody),
+        (status = 429, description = "Rate limit exceeded, see Retry-After", body = ErrorBody),
+        (status = 500, description = "Database failure", body = ErrorBody),
+        (status = 503, description = "Database or api key store unreachable", body = ErrorBody),
+    )
+)]
+/// Only the tables the caller's key may read are listed
+async fn schema(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<SchemaQuery>,
+) -> Result<Json<DatabaseSchema>, ApiError> {
+    let policy = authorize(&state, &headers).await?;
+    let source = state
+        .datasources
+        .named(query.datasource.as_deref())
+        .map_err(|err| ApiError::new(StatusCode::BAD_REQUEST, err.to_string()))?;
+    if let Some(policy) = &policy {
+        policy.check_datasource(&source.name)?;
+    }
+    let schema = source.chain.get_db_info().await?;
+    Ok(Json(match policy {
+        Some(policy) => policy.filter_schema(schema),
+        None => schema,
+    }))
+}
+
+/// Datasources the caller's key may use
+#[utoipa::path(
+    get,
+    path = "/datasources",
+    responses(
+        (status = 200, description = "Configured datasources", body = Vec<DatasourceInfo>),
+        (status = 401, description = "Missing or unknown api key", body = ErrorBody),
+        (status = 429, description = "Rate limit exceeded, see Retry-After", body = ErrorBody),
+        (status = 503, description = "Api key store unreachable", body = ErrorBody),
+    )
+)]
+async fn datasources(State(state): State<AppState>, headers: HeaderMap) -> Result<Json<Vec<DatasourceInfo>>, ApiError> {
+    let policy = authorize(&state, &headers).await?;
+    let visible = state
+        .datasources
+        .list()
+        .into_iter()
+        .filter(|info| policy.as_ref().is_none_or(|policy| policy.check_datasource(&info.name).is_ok()))
+        .collect();
+    Ok(Json(visible))
+}
+
+/// Pings every datasource pool, reconnecting the ones whose server went away.
+/// Open without an api key, for load balancers and orchestrators.
+#[utoipa::path(
+    get,
+    path = "/health",
+    security(()),
+    responses(
+        (status = 200, description = "Every datasource answers", body = Vec<DatasourceHealth>),
+        (status = 503, description = "At least one datasource does not answer", body = Vec<DatasourceHealth>),
+    )
+)]
+async fn health(State(state): State<AppState>) -> (StatusCode, Json<Vec<DatasourceHealth>>) {
+    let health = state.datasources.health().await;
+    let status = match health.iter().all(|source| source.healthy) {
+        true => StatusCode::OK,
+        false => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (status, Json(health))
+}
+
+#[utoipa::path(
+    get,
+    path = "/sessions/{id}",
+    params(("id" = String, Path, description = "Session id")),
+    responses(
+        (status = 200, description = "Conversation with all turns", body = ConversationSession),
+        (status = 401, description = "Missing or unknown api key", body = ErrorBody),
+        (status = 403, description = "The key may not use this datasource or these tables", body = ErrorBody),
+        (status = 429, description = "Rate limit exceeded, see Retry-After", body = ErrorBody),
+        (status = 404, description = "Unknown session, or one started with another api key", body = ErrorBody),
+        (status = 503, description = "Api key store or session store unavailable", body = ErrorBody),
+    )
+)]
+async fn session(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<ConversationSession>, ApiError> {
+    let policy = authorize(&state, &headers).await?;
+    Ok(Json(load_session(&state, &policy, Some(&id)).await?))
+}
+
+
+#[cfg(test)]
+mod test {
+    use utoipa::OpenApi;
+
+    use super::ApiDoc;
+
+    #[test]
+    fn test_openapi_lists_routes() {
+        let doc = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        for path in ["/ask", "/ask/stream", "/sql/generate", "/sql/execute", "/schema", "/datasources", "/health", "/sessions/{id}"] {
+            assert!(doc["paths"].get(path).is_some(), "missing {}", path);
+        }
+        assert!(doc["components"]["schemas"].get("ResultSet").is_some());
+    }
+}
@@ -1,4 +1,6 @@
 pub mod configuration;
 pub mod datasource;
 pub mod trait_req_impl;
-pub mod agent;
\ No newline at end of file
+pub mod agent;
+pub mod interface;
+pub mod error;
\ No newline at end of file
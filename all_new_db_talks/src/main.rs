@@ -1,18 +1,13 @@
-use std::io::{self, Write};
+use all_new_db_talks::interface::cli::Cli;
+use clap::Parser;
 
-use all_new_db_talks::{agent::text_to_sql::TextToSqlChain, trait_req_impl::chain::Chain};
 
+fn main() {
+    let cli = Cli::parse();
 
-#[tokio::main]
-async fn main() {
-    let processor = TextToSqlChain::initialze().await.unwrap();
-    let mut input = String::new();
-    println!("How can i help you: ");
-    io::stdout().flush().unwrap();
-    io::stdin().read_line(&mut input)
-        .expect("Failed to read line");
-    let output = processor.run(input)
-        .await
-        .unwrap();
-    println!("Response {:?}", output);
+    let runtime = tokio::runtime::Runtime::new().expect("Failed to start tokio runtime");
+    if let Err(err) = runtime.block_on(cli.run()) {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
 }
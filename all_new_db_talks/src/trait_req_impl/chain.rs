@@ -1,12 +1,14 @@
-use anyhow::Error;
 use async_trait::async_trait;
 
+use crate::{configuration::app_config::AppConfig, error::TalkError};
+
 #[async_trait]
 pub trait Chain {
-    async fn initialze() -> Result<Box<dyn Chain + Send>, Error>
+    /// `config` is loaded and validated once by the caller
+    async fn initialze(config: &AppConfig) -> Result<Box<dyn Chain + Send>, TalkError>
         where
             Self: Sized;
     
-    async fn run(&self, input: String) -> Result<String, Error>;
+    async fn run(&self, input: String) -> Result<String, TalkError>;
 
 }
\ No newline at end of file
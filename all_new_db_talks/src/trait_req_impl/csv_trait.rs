@@ -1,8 +1,16 @@
-use anyhow::Error;
 use async_trait::async_trait;
 use datafusion::arrow::array::RecordBatch;
 
+use crate::{datasource::result_set::ResultSet, error::TalkError};
+
 #[async_trait]
 pub trait CsvImplTrait {
-    async fn execute_csv_query(&self, the_query: String) -> String;
+    /// Arrow batches of the query, for callers that format or export them themselves
+    async fn execute_csv_query(&self, the_query: String) -> Result<Vec<RecordBatch>, TalkError>;
+
+    /// Typed rows, the same shape database queries return
+    async fn execute_csv_typed(&self, the_query: String) -> Result<ResultSet, TalkError> {
+        let batches = self.execute_csv_query(the_query).await?;
+        Ok(ResultSet::from_batches(&batches)?)
+    }
 }
\ No newline at end of file